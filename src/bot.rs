@@ -0,0 +1,227 @@
+use nalgebra::Vector3;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::phys::{Physics, PinSet, Throw};
+
+// Averages a computer bowler can be given
+pub const MIN_AVERAGE: u16 = 60;
+pub const MAX_AVERAGE: u16 = 230;
+// Spread of the ball at the pins (in boards) that bowls a given average, measured by letting
+// bots bowl on the house pattern at the default physics rate. Tougher patterns bring the scores
+// down. `cargo test --release calibration -- --ignored` bowls the games again to check the table
+const CALIBRATION: [(u16, f32); 8] =
+    [(60, 31.0), (90, 19.3), (110, 13.0), (125, 10.6), (150, 7.3), (180, 5.2), (205, 1.6), (230, 0.62)];
+// speed and rev spread that goes with every board of accuracy spread
+const CONSISTENCY_PER_BOARD: f32 = 0.003;
+// accuracy spread at which spares are shot twice as wide as strikes
+const SPARE_SHOOTING_BOARDS: f32 = 20.0;
+// the pocket is between the head pin and the 3 pin for a right-hander
+const POCKET: f32 = 0.09;
+// strike ball: where the bot stands, speed, rev rate and axis rotation
+const STRIKE_BOARD: u8 = 11;
+const STRIKE_SPEED: f32 = 8.0;
+const STRIKE_REV_RATE: f32 = 300.0;
+const STRIKE_AXIS_ROTATION: f32 = 30.0;
+// spares are shot straight, a ball that doesn't hook doesn't care about the oil
+const SPARE_SPEED: f32 = 8.5;
+const SPARE_REV_RATE: f32 = 150.0;
+// how far the strike ball hooks before the bot has seen it roll, and how fast it adjusts
+const FIRST_READ: f32 = 0.35;
+const ADJUSTMENT: f32 = 0.5;
+
+// How well a computer bowler throws
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Skill {
+    pub average: u16,
+    // spread of where the ball crosses the pin deck, in boards
+    pub accuracy: f32,
+    // spread of the release speed and rev rate, as a fraction of what was meant
+    pub consistency: f32,
+    // 0 to 1: a bowler at 1 shoots spares as well as strikes, at 0 twice as wide
+    pub spare_shooting: f32,
+}
+
+impl Skill {
+    pub fn for_average(average: u16) -> Self {
+        let average = average.clamp(MIN_AVERAGE, MAX_AVERAGE);
+        let accuracy = CALIBRATION
+            .windows(2)
+            .find(|pair| average <= pair[1].0)
+            .map(|pair| {
+                let ((low, low_spread), (high, high_spread)) = (pair[0], pair[1]);
+                let t = (average - low) as f32 / (high - low) as f32;
+                low_spread + (high_spread - low_spread) * t
+            })
+            .unwrap_or(CALIBRATION[CALIBRATION.len() - 1].1);
+        Self { average, accuracy: 0.0, consistency: 0.0, spare_shooting: 1.0 }.with_accuracy(accuracy)
+    }
+
+    // The rest of the profile follows the accuracy: a wild bowler is also an inconsistent one.
+    // Set the fields after it for a bowler who isn't
+    pub fn with_accuracy(self, accuracy: f32) -> Self {
+        Self {
+            accuracy,
+            consistency: accuracy * CONSISTENCY_PER_BOARD,
+            spare_shooting: (1.0 - accuracy / SPARE_SHOOTING_BOARDS).clamp(0.0, 1.0),
+            ..self
+        }
+    }
+}
+
+// Computer bowler: picks a line for the pins left standing, then misses it by as much as its skill says.
+// It throws a hook at a full rack and a straight ball at spares, like most league bowlers.
+#[derive(Debug, Clone)]
+pub struct Bot {
+    pub skill: Skill,
+    rng: StdRng,
+    // how far the strike ball moves left on its way down, read from the balls thrown so far
+    hook: f32,
+    // the last strike ball, to see how much it hooked
+    strike_ball: Option<Throw>,
+}
+
+impl Bot {
+    pub fn new(skill: Skill) -> Self {
+        Self { skill, rng: StdRng::from_entropy(), hook: FIRST_READ, strike_ball: None }
+    }
+
+    // The same seed gives the same misses, for runs that have to come out the same every time
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    // The throw for the pins standing on the deck
+    pub fn throw(&mut self, physics: &Physics, standing: PinSet) -> Throw {
+        let Some(front) = front_pin(physics, standing) else { return Throw::default() };
+        let lane = physics.lane();
+        let edge = lane.lane_width / 2.0 - lane.board_width() * 3.0;
+        let full_rack = standing == physics.all_pins();
+        let (target, position, spread) = if full_rack {
+            //always from the same board, only the line changes with the hook
+            (front.x - POCKET - self.hook, lane.board_x(STRIKE_BOARD), self.skill.accuracy)
+        } else {
+            let target = spare_target(physics, standing, front);
+            //cross-lane: the further out the spare, the further over the bowler stands
+            (target, (-target / 2.0).clamp(-edge, edge), self.skill.accuracy * (2.0 - self.skill.spare_shooting))
+        };
+        let miss = self.gaussian() * spread * lane.board_width();
+        let launch_angle = (target + miss - position).atan2(front.z).to_degrees();
+        let consistency = self.skill.consistency;
+        let throw = if full_rack {
+            Throw {
+                position,
+                speed: STRIKE_SPEED * (1.0 + self.gaussian() * consistency),
+                launch_angle,
+                rev_rate: STRIKE_REV_RATE * (1.0 + self.gaussian() * consistency),
+                axis_rotation: STRIKE_AXIS_ROTATION,
+                ..Throw::default()
+            }
+        } else {
+            Throw {
+                position,
+                speed: SPARE_SPEED * (1.0 + self.gaussian() * consistency),
+                launch_angle,
+                rev_rate: SPARE_REV_RATE,
+                axis_rotation: 0.0,
+                ..Throw::default()
+            }
+        };
+        self.strike_ball = full_rack.then_some(throw);
+        throw
+    }
+
+    // After the ball reached the pins: whatever the strike ball did past the straight line was hook
+    pub fn observe(&mut self, physics: &Physics) {
+        let (Some(throw), Some(entry)) = (self.strike_ball.take(), physics.ball_entry()) else { return };
+        if entry.x.abs() > physics.lane().lane_width / 2.0 {
+            return;
+        }
+        let straight = throw.position + throw.launch_angle.to_radians().tan() * entry.z;
+        self.hook += (entry.x - straight - self.hook) * ADJUSTMENT;
+    }
+
+    // standard normal, Box-Muller
+    fn gaussian(&mut self) -> f32 {
+        let u: f32 = self.rng.gen_range(f32::EPSILON..1.0);
+        let v: f32 = self.rng.gen();
+        (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+    }
+}
+
+// the standing pin nearest the bowler
+fn front_pin(physics: &Physics, standing: PinSet) -> Option<Vector3<f32>> {
+    standing.iter().filter_map(|pin| physics.pin_spot(pin)).min_by(|a, b| a.z.total_cmp(&b.z))
+}
+
+// Hit the front pin on the side the other pins are on, so it gets carried into them
+fn spare_target(physics: &Physics, standing: PinSet, front: Vector3<f32>) -> f32 {
+    let spots: Vec<Vector3<f32>> = standing.iter().filter_map(|pin| physics.pin_spot(pin)).collect();
+    let centre = spots.iter().map(|spot| spot.x).sum::<f32>() / spots.len() as f32;
+    let reach = physics.lane().board_width() * 2.5;
+    front.x + (centre - front.x).clamp(-reach, reach)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::headless::Headless;
+    use super::super::oil::OilPattern;
+    use super::super::physics_config::PhysicsConfig;
+    use super::super::players::{Lineup, Player};
+    use super::super::variant::Variant;
+    use super::*;
+
+    // games a step of the calibration is bowled again with, and how far off its average may come out
+    const CALIBRATION_GAMES: u64 = 30;
+    const CALIBRATION_TOLERANCE: f32 = 15.0;
+
+    // Average of the bot over seeded games on the house pattern, the way the calibration was measured
+    fn bowled(skill: Skill, games: u64) -> f32 {
+        let pattern = OilPattern::parse(include_str!("../res/patterns/house.txt")).unwrap();
+        let total: u16 = (0..games)
+            .map(|seed| {
+                let mut player = Player::parse("Bot@100", 0).unwrap();
+                player.bot = Some(Bot::new(skill));
+                let lineup = Lineup::new(vec![player]).unwrap();
+                let mut run = Headless::new(lineup, Variant::default(), pattern.clone(), PhysicsConfig::default(), 120, seed);
+                run.play(std::iter::empty()).unwrap();
+                run.lineup().players()[0].score.total()
+            })
+            .sum();
+        total as f32 / games as f32
+    }
+
+    #[test]
+    fn better_bowlers_miss_by_less() {
+        let skills = (MIN_AVERAGE..=MAX_AVERAGE).step_by(10).map(Skill::for_average).collect::<Vec<_>>();
+        for pair in skills.windows(2) {
+            assert!(pair[1].accuracy < pair[0].accuracy, "{pair:?}");
+            assert!(pair[1].consistency < pair[0].consistency && pair[1].spare_shooting >= pair[0].spare_shooting);
+        }
+        assert_eq!(Skill::for_average(0), Skill::for_average(MIN_AVERAGE));
+        assert_eq!(Skill::for_average(300), Skill::for_average(MAX_AVERAGE));
+    }
+
+    #[test]
+    fn profile_can_be_set() {
+        let skill = Skill::for_average(150).with_accuracy(2.0);
+        assert_eq!(skill.average, 150);
+        assert_eq!(skill.accuracy, 2.0);
+        assert_eq!(skill.consistency, 2.0 * CONSISTENCY_PER_BOARD);
+        assert_eq!(skill.spare_shooting, 0.9);
+        // a straight shooter who can't pick up a spare
+        let skill = Skill { spare_shooting: 0.0, ..skill };
+        assert_eq!(Bot::new(skill).skill.spare_shooting, 0.0);
+    }
+
+    // Minutes of bowling, run it after changing the bot or the physics and fix the table if it fails
+    #[test]
+    #[ignore]
+    fn calibration() {
+        for (average, _) in CALIBRATION {
+            let bowled = bowled(Skill::for_average(average), CALIBRATION_GAMES);
+            println!("{average}: {bowled:.1}");
+            assert!((bowled - average as f32).abs() < CALIBRATION_TOLERANCE, "{average} bowls {bowled:.1}");
+        }
+    }
+}
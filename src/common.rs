@@ -2,7 +2,7 @@
 use std::default;
 //source: https://github.com/jack1232/wgpu11
 //based on https://whoisryosuke.com/blog/2022/render-pipelines-in-wgpu-and-rust#multiple-models
-use std:: {iter, mem ,collections::HashMap};
+use std:: {iter, mem ,collections::{BTreeMap, HashMap}};
 use cgmath::{ Matrix, Matrix4, Quaternion, SquareMatrix, Vector3 };
 use cgmath::prelude::*;
 
@@ -35,10 +35,61 @@ mod camera;
 mod control;
 #[path="../src/phys.rs"]
 mod phys;
+#[path="../src/pin_shape.rs"]
+mod pin_shape;
+#[path="../src/physics_config.rs"]
+mod physics_config;
+#[path="../src/debug_render.rs"]
+mod debug_render;
+#[path="../src/scoring.rs"]
+mod scoring;
+#[path="../src/pinsetter.rs"]
+mod pinsetter;
+#[path="../src/game.rs"]
+mod game;
+#[path="../src/players.rs"]
+mod players;
+#[path="../src/lane.rs"]
+mod lane;
+#[path="../src/oil.rs"]
+mod oil;
+#[path="../src/delivery.rs"]
+mod delivery;
+#[path="../src/leave.rs"]
+mod leave;
+#[path="../src/variant.rs"]
+mod variant;
+#[path="../src/practice.rs"]
+mod practice;
+#[path="../src/bot.rs"]
+mod bot;
+#[path="../src/league.rs"]
+mod league;
+#[path="../src/tournament.rs"]
+mod tournament;
+#[path="../src/history.rs"]
+mod history;
+#[path="../src/save.rs"]
+mod save;
+#[path="../src/headless.rs"]
+mod headless;
 use resources::model::{texture::Texture, DrawModel,DrawLight,Instance, InstanceRaw, Model, ModelVertex, Vertex,Object,Globals,Locals};
 use resources::{UniformPool};
 use camera::{Camera, CameraController, CameraUniform, Projection};
+use phys::{EntityId, Role};
 const ANIMATION_SPEED:f32 = 1.0;
+//physics steps per second, the same on every monitor
+const PHYSICS_RATE: u32 = 120;
+const MIN_PHYSICS_RATE: u32 = 30;
+const MAX_PHYSICS_RATE: u32 = 1000;
+//after a longer hitch the physics doesn't try to catch up on all of it
+const MAX_FRAME_TIME: std::time::Duration = std::time::Duration::from_millis(250);
+//size of ball.obj and pin.obj, measured from their origin at the bottom
+const BALL_MODEL_RADIUS: f32 = 0.17;
+const BALL_MODEL_CENTER: f32 = 0.168;
+const PIN_MODEL_RADIUS: f32 = 0.0605;
+const PIN_MODEL_HALF_HEIGHT: f32 = 0.19;
+const PIN_MODEL_CENTER: f32 = 0.1936;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -55,33 +106,53 @@ struct State<'a> {
     init: context::InitWgpu<'a>,
     render_pipeline: wgpu::RenderPipeline,
     light_pipeline: wgpu::RenderPipeline,
-    objects: Vec<Object>,
+    line_pipeline: wgpu::RenderPipeline,
+    objects: BTreeMap<EntityId, Object>,
     global_bind_group_layout: BindGroupLayout,
     window: &'a Window,
     depth_texture: Texture,
     camera: Camera,
     obj_bind_group_layout: wgpu::BindGroupLayout,
-    obj_bind_groups: HashMap<usize,wgpu::BindGroup>,
+    obj_bind_groups: HashMap<EntityId,wgpu::BindGroup>,
     uniform_pool: UniformPool,
     global_bind_group: wgpu::BindGroup,
     camera_controller: CameraController,
     camera_uniform: CameraUniform,
     global_uniform_buffer: wgpu::Buffer,
-    instance_buffers: HashMap<usize, wgpu::Buffer>,
+    instance_buffers: HashMap<EntityId, wgpu::Buffer>,
     mouse_pressed: bool,
     physics: phys::Physics,
+    game: game::Game,
+    delivery: delivery::Delivery,
+    //league books and the file they are kept in, games are entered as they finish
+    league: Option<(std::path::PathBuf, league::League)>,
+    //set while a tournament runs, every game over starts its next match
+    tournament: Option<tournament::Tournament>,
+    //every finished game is saved here
+    history: Option<history::History>,
+    //the file the game in progress is saved to and the oil pattern file it is bowled on
+    autosave: Option<(std::path::PathBuf, String)>,
+    //the physics config file F5 reloads
+    physics_config: Option<std::path::PathBuf>,
+    //a reload with new sizes waits here for the rack to be set, building the pins again mid-ball loses the count
+    held_physics_config: Option<physics_config::PhysicsConfig>,
+    //F3 draws what physics sees over the scene
+    debug_overlay: bool,
+    debug_lines: debug_render::DebugLines,
+    //grows with the most lines drawn so far
+    debug_buffer: Option<wgpu::Buffer>,
     projection: Projection,
 }
 impl <'a>State <'a>{
     
-    async fn new(window: &'a Window) -> Self {        
+    async fn new(window: &'a Window, lineup: players::Lineup, pattern: oil::OilPattern, variant: variant::Variant, practice: Option<practice::Practice>) -> Self {        
         let init =  context::InitWgpu::init_wgpu(window).await;
-        const NUMBER_OF_PINS: i32 = 10;
         let shader_module = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Normal Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
         });
-          let camera = camera::Camera::new((0.0, 1.0, 0.0), cgmath::Deg(90.0), cgmath::Deg(0.0));
+          //behind the foul line, looking down the lane
+          let camera = camera::Camera::new((0.0, 1.4, -2.5), cgmath::Deg(90.0), cgmath::Deg(-10.0));
           let projection =
               camera::Projection::new(init.config.width, init.config.height, cgmath::Deg(45.0), 0.1, 100.0);
           let camera_controller = camera::CameraController::new(4.0, 0.4);
@@ -309,66 +380,80 @@ impl <'a>State <'a>{
                 multiview: None,
             });
 
+        //debug lines are in world space and drawn over everything, they only need the camera
+        let line_shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Line Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("line.wgsl").into()),
+        });
+        let line_pipeline_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Line Pipeline"),
+            bind_group_layouts: &[&global_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let line_pipeline =
+            init.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Line Pipeline"),
+                layout: Some(&line_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &line_shader,
+                    entry_point: "vs_main",
+                    buffers: &[debug_render::LineVertex::desc()],
+                    compilation_options: Default::default(),
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Texture::DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: Default::default(),
+                    bias: Default::default(),
+                }),
+                multisample,
+                cache:None,
+                fragment: Some(wgpu::FragmentState {
+                    module: &line_shader,
+                    compilation_options: Default::default(),
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: init.config.format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            });
+
 
         //Physics
-        let mut physics = phys::Physics::new();
+        let mut physics = phys::Physics::new(lane::LaneSpec::REGULATION);
+        physics.set_oil_pattern(pattern);
+        physics.set_variant(variant.clone());
         physics.build_colliders();
+        println!("Oil pattern: {}", physics.oil_pattern());
+        println!("Game: {variant}");
 
         let uniform_pool = UniformPool::new("[Phong] Locals", local_size);
-        let mut objects: Vec<Object> =  Vec::new();
-
-
-        /*let floor_model =
-            resources::load_model("floor.obj", &init.device, &init.queue)
-                .await
-                .unwrap();
-        let mut floor_instances = Vec::new();
-        floor_instances.push({Instance{position:Vector3{x: 0.0,y:0.0,z:0.0},rotation:Quaternion::from_axis_angle(cgmath::Vector3::unit_y(), cgmath::Deg(0.0)),scale:Vector3{x:1.0,y:1.0,z:1.0}}});
-        objects.push(Object::new(floor_model, floor_instances,String::from("floor")));
-*/
-        //creating objects
-       
-        let ball_model =
-            resources::load_model("ball.obj", &init.device, &init.queue)
-                .await
-                .unwrap();
-        
-        
-        
-            
-        let mut ball_instances = Vec::new();
-        ball_instances.push({Instance{position:Vector3{x: 0.0,y:0.0,z:0.0},rotation:Quaternion::from_axis_angle(cgmath::Vector3::unit_y(), cgmath::Deg(0.0)),scale:Vector3{x:1.0,y:1.0,z:1.0}}});
-        //Instancing... if it only worked
-        /*let pin_instances = (0..NUMBER_OF_PINS)
-        .map(|i| {
-            let position = cgmath::Vector3{x: (i%4) as f32 * 0.6 - (i/4) as f32 *0.3, y:0.0, z: (i/4) as f32 * -0.8 + 10.0};
-
-            let rotation = cgmath::Quaternion::from_axis_angle(cgmath::Vector3::unit_y(), cgmath::Deg(0.0));
-
-            let scale = cgmath::Vector3 { x: (5.0), y: (5.0), z: (5.0) };
-            Instance { position, rotation,scale }
-        })
-        .collect::<Vec<_>>();*/
-        //Unfortunetly have to do this teribleness because currently it's setup that all instances receive the same transformation, and i don't have enough time
-        //Too bad!
-        objects.push(Object::new(ball_model, ball_instances,String::from("Ball")));
-        for i in 0..10 {
-            let pin_model =
-            resources::load_model("pin.obj", &init.device, &init.queue)
-                .await
-                .unwrap();
-            let mut pin_instances = Vec::new();
-            pin_instances.push({Instance{position:Vector3{x: 0.0,y:0.0,z:0.0 },rotation:Quaternion::from_axis_angle(cgmath::Vector3::unit_y(), cgmath::Deg(0.0)),scale:Vector3{x:1.0,y:1.0,z:1.0}}});
-            objects.push(Object::new(pin_model, pin_instances,String::from("Pin")));
-            //I'm sorry
-        }
-
-        println!("{}", objects.len());
+        let mut objects: BTreeMap<EntityId, Object> = BTreeMap::new();
+        //creating objects, one for every entity that is drawn
+        for (entity, role) in physics.entities() {
+            if let Some(object) = entity_object(role, &variant, physics.lane(), &init.device, &init.queue).await {
+                objects.insert(entity, object);
+            }
+        }
+
         let instance_buffers = HashMap::new();
+        let mut game = game::Game::new(game::SystemClock::new(), lineup, variant);
+        if let Some(practice) = practice {
+            game.start_practice(&mut physics, practice);
+        }
         Self {
             init,
             render_pipeline,
             light_pipeline,
+            line_pipeline,
             global_bind_group,
             objects,
             window,
@@ -383,7 +468,18 @@ impl <'a>State <'a>{
             global_uniform_buffer,
             instance_buffers,
             mouse_pressed: false,
+            delivery: delivery::Delivery::new(*physics.lane()),
             physics,
+            game,
+            league: None,
+            tournament: None,
+            history: history::History::open(),
+            autosave: None,
+            physics_config: None,
+            held_physics_config: None,
+            debug_overlay: false,
+            debug_lines: debug_render::DebugLines::new(),
+            debug_buffer: None,
             projection
         }
     }
@@ -406,6 +502,30 @@ impl <'a>State <'a>{
     #[allow(unused_variables)]
     fn input(&mut self, event: &WindowEvent) -> bool {
         match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F5),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                self.reload_physics_config();
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F3),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                self.debug_overlay = !self.debug_overlay;
+                true
+            }
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -414,7 +534,7 @@ impl <'a>State <'a>{
                         ..
                     },
                 ..
-            } => control::process_keyboard(*key, *state, &mut self.camera_controller, &mut self.physics,&self.camera),
+            } => control::process_keyboard(*key, *state, &mut self.camera_controller, &mut self.physics, &mut self.game, &mut self.delivery),
             WindowEvent::MouseWheel { delta, .. } => {
                 self.camera_controller.process_scroll(delta);
                 true
@@ -432,28 +552,174 @@ impl <'a>State <'a>{
     }
     fn fixed_update(&mut self){
         self.physics.simulate();
+        //RUST_LOG=debug shows what hit what
+        for impact in self.physics.impacts() {
+            log::debug!("{impact}");
+        }
+        self.game.update(&mut self.physics);
+        //the HUD for now is the console
+        for event in self.game.drain_events() {
+            match event {
+                //the rack is set for the next ball
+                game::GameEvent::PhaseChanged(game::GamePhase::Aiming) => {
+                    if let Some(config) = self.held_physics_config.take() {
+                        self.physics.set_config(config);
+                    }
+                    self.save_progress();
+                }
+                game::GameEvent::PhaseChanged(_) => {}
+                game::GameEvent::BallScored { .. } => println!("{event}\n{}", self.game.lineup()),
+                game::GameEvent::GameOver { .. } => {
+                    println!("{event}");
+                    self.save_game();
+                    self.clear_progress();
+                    self.record_league_games();
+                    self.next_match();
+                }
+                game::GameEvent::PracticeBall { .. } => {
+                    println!("{event}");
+                    if let Some(practice) = self.game.practice() {
+                        println!("{practice}");
+                    }
+                }
+                _ => println!("{event}"),
+            }
+        }
+    }
+    fn save_game(&self) {
+        let Some(history) = &self.history else { return };
+        let record = history::GameRecord::new(self.game.lineup(), self.game.variant().kind, &self.physics.oil_pattern().name);
+        match history.save(&record) {
+            Ok(id) => println!("Game saved as {id}"),
+            Err(e) => eprintln!("game history {}: {e}", history.dir().display()),
+        }
+    }
+    fn save_progress(&self) {
+        let Some((path, oil)) = &self.autosave else { return };
+        let saved = save::SavedGame::new(self.game.lineup(), self.game.variant().kind, oil, self.physics.pins_on_deck());
+        let written = path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|_| std::fs::write(path, saved.to_text()));
+        if let Err(e) = written {
+            eprintln!("saved game {}: {e}", path.display());
+        }
+    }
+    fn update_debug_lines(&mut self) {
+        self.debug_lines.collect(&self.physics);
+        let contents: &[u8] = bytemuck::cast_slice(&self.debug_lines.vertices);
+        match &self.debug_buffer {
+            Some(buffer) if buffer.size() >= contents.len() as wgpu::BufferAddress => {
+                self.init.queue.write_buffer(buffer, 0, contents);
+            }
+            _ => {
+                self.debug_buffer = Some(self.init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Debug Lines"),
+                    contents,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                }));
+            }
+        }
+    }
+    //a file that doesn't load leaves physics as it was
+    fn reload_physics_config(&mut self) {
+        let Some(path) = &self.physics_config else {
+            println!("No physics config to reload, start with --physics=<file>");
+            return;
+        };
+        match load_physics_config(path) {
+            Ok(config) if self.physics.resizes(&config) && self.game.phase() != game::GamePhase::Aiming => {
+                self.held_physics_config = Some(config);
+                println!("Physics config {} reloaded, the new sizes come in once the rack is set", path.display());
+            }
+            Ok(config) => {
+                self.held_physics_config = None;
+                self.physics.set_config(config);
+                println!("Physics config {} reloaded", path.display());
+            }
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+    fn clear_progress(&self) {
+        let Some((path, _)) = &self.autosave else { return };
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => eprintln!("saved game {}: {e}", path.display()),
+            _ => {}
+        }
+    }
+    fn record_league_games(&mut self) {
+        let Some((path, league)) = &mut self.league else { return };
+        for player in self.game.lineup().players() {
+            league.record(&player.name, player.score.total());
+        }
+        if let Err(e) = std::fs::write(&*path, league.to_text()) {
+            eprintln!("league file {}: {e}", path.display());
+        }
+        println!("{league}");
     }
-    fn update(&mut self, dt: std::time::Duration) {
+    fn next_match(&mut self) {
+        let Some(tournament) = &mut self.tournament else { return };
+        tournament.record(self.game.lineup());
+        println!("{tournament}");
+        if let Some(lineup) = tournament.lineup() {
+            self.game.start_match(lineup);
+        }
+    }
+    //alpha is how far the render is between the last two physics steps
+    fn update(&mut self, dt: std::time::Duration, alpha: f32) {
         self.camera_controller.update_camera(&mut self.camera,dt);
+        //the camera is the bowler's eyes
+        let eye = self.camera.position;
+        self.physics.move_bowler(nalgebra::vector![eye.x, eye.y, eye.z]);
+        self.delivery.update(dt.as_secs_f32());
+        if matches!(self.delivery.stage(), delivery::DeliveryStage::Power | delivery::DeliveryStage::Spin) {
+            //the meter is redrawn in place on the console
+            let filled = (self.delivery.meter() * 20.0).round() as usize;
+            print!("\r[{:<20}]", "#".repeat(filled));
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+        }
         self.camera_uniform.update_view_proj(&self.camera,&self.projection);
         self.init.queue.write_buffer(
             &self.global_uniform_buffer,
             0,
             bytemuck::cast_slice(&[self.camera_uniform]),
         );
+        if self.debug_overlay {
+            self.update_debug_lines();
+        }
+        self.sync_objects();
         // update uniform buffer
         let dt = ANIMATION_SPEED * dt.as_secs_f32();
         //transforms on model
         // Update local uniforms
-        let mut obj_index = 0;
-        for obj in &mut self.objects {
-            let translation = self.physics.get_translation(obj_index);
-            let rotation = self.physics.get_rotation(obj_index);
+        //the ball takes the colour of whoever is bowling
+        let [r, g, b] = self.game.lineup().active().color;
+        if let Some(ball) = self.physics.entity(Role::Ball).and_then(|ball| self.objects.get_mut(&ball)) {
+            ball.locals.color = [r, g, b, 1.0];
+        }
+        for (&entity, obj) in &mut self.objects {
+            let (Some(translation), Some(rotation)) = (self.physics.get_translation(entity, alpha), self.physics.get_rotation(entity, alpha)) else { continue };
             obj.locals.create_transforms(translation, rotation, [1.0,1.0,1.0]);
             self
                 .uniform_pool
-                .update_uniform(obj_index, obj.locals, &self.init.queue);
-            obj_index += 1;
+                .update_uniform(entity, obj.locals, &self.init.device, &self.init.queue);
+        }
+    }
+    //render objects follow the entities physics has, whatever spawned or despawned them
+    fn sync_objects(&mut self) {
+        let entities: Vec<(EntityId, Role)> = self.physics.entities().collect();
+        let gone: Vec<EntityId> = self.objects.keys().filter(|entity| !entities.iter().any(|(id, _)| id == *entity)).copied().collect();
+        for entity in gone {
+            self.objects.remove(&entity);
+            self.obj_bind_groups.remove(&entity);
+            self.instance_buffers.remove(&entity);
+            self.uniform_pool.free(entity);
+        }
+        for (entity, role) in entities {
+            if self.objects.contains_key(&entity) {
+                continue;
+            }
+            let object = pollster::block_on(entity_object(role, self.physics.variant(), self.physics.lane(), &self.init.device, &self.init.queue));
+            if let Some(object) = object {
+                self.objects.insert(entity, object);
+            }
         }
     }
 
@@ -514,13 +780,9 @@ impl <'a>State <'a>{
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
-            if (self.uniform_pool.buffers.len() < self.objects.len()) {
-                self.uniform_pool.alloc_buffers(self.objects.len(), &self.init.device);
-            }
-            let mut obj_index = 0;
-            for obj in &self.objects{
-                let local_buffer =&self.uniform_pool.buffers[obj_index];
-                self.obj_bind_groups.entry(obj_index).or_insert_with(||{
+            for (entity, obj) in &self.objects{
+                let Some(local_buffer) = self.uniform_pool.buffers.get(entity) else { continue };
+                self.obj_bind_groups.entry(*entity).or_insert_with(||{
                     self.init.device.create_bind_group(&wgpu::BindGroupDescriptor {
                         label: Some("Instance bind group"),
                         layout: &self.obj_bind_group_layout,
@@ -538,7 +800,7 @@ impl <'a>State <'a>{
                         ],
                     })
                 });
-                self.instance_buffers.entry(obj_index).or_insert_with(|| {
+                self.instance_buffers.entry(*entity).or_insert_with(|| {
                     // We condense the matrix properties into a flat array (aka "raw data")
                     // (which is how buffers work - so we can "stride" over chunks)
                     let instance_data = obj
@@ -556,23 +818,32 @@ impl <'a>State <'a>{
 
                     instance_buffer
                 });
-                obj_index+=1;
             }
                
                 
-            obj_index = 0;
             render_pass.set_pipeline(&self.light_pipeline);
             
-            render_pass.draw_light_model(&self.objects[0].model, &self.global_bind_group,&self.obj_bind_groups.get(&0).expect("No obj bind group found for lighting"));
+            if let Some(ball) = self.physics.entity(Role::Ball) {
+                if let (Some(obj), Some(bind_group)) = (self.objects.get(&ball), self.obj_bind_groups.get(&ball)) {
+                    render_pass.draw_light_model(&obj.model, &self.global_bind_group, bind_group);
+                }
+            }
             
             render_pass.set_pipeline(&self.render_pipeline);
             render_pass.set_bind_group(0, &self.global_bind_group, &[]);
 
             
-            for obj in &self.objects{
-                render_pass.set_vertex_buffer(1, self.instance_buffers[&obj_index].slice(..));
-                render_pass.draw_model_instanced(&obj.model, 0.. obj.instances.len() as u32, &self.obj_bind_groups[&obj_index]);
-                obj_index+=1;
+            for (entity, obj) in &self.objects{
+                let (Some(instances), Some(bind_group)) = (self.instance_buffers.get(entity), self.obj_bind_groups.get(entity)) else { continue };
+                render_pass.set_vertex_buffer(1, instances.slice(..));
+                render_pass.draw_model_instanced(&obj.model, 0.. obj.instances.len() as u32, bind_group);
+            }
+            let count = self.debug_lines.vertices.len() as u32;
+            if let (true, Some(buffer), 1..) = (self.debug_overlay, &self.debug_buffer, count) {
+                render_pass.set_pipeline(&self.line_pipeline);
+                render_pass.set_bind_group(0, &self.global_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, buffer.slice(..));
+                render_pass.draw(0..count, 0..1);
             }
         }
             
@@ -584,16 +855,310 @@ impl <'a>State <'a>{
     }
 }
 
+//the model an entity is drawn with, the bowler is never drawn
+async fn entity_object(role: Role, variant: &variant::Variant, lane: &lane::LaneSpec, device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Object> {
+    //the models have their origin at the bottom, bodies at their centre; both are resized to the variant
+    let (model, instance, name) = match role {
+        Role::Ball => {
+            let ball_scale = variant.ball.radius / BALL_MODEL_RADIUS;
+            let model = resources::load_model("ball.obj", device, queue).await.unwrap();
+            (model, Instance{position:Vector3{x: 0.0,y:-BALL_MODEL_CENTER * ball_scale,z:0.0},rotation:Quaternion::from_axis_angle(cgmath::Vector3::unit_y(), cgmath::Deg(0.0)),scale:Vector3{x:ball_scale,y:ball_scale,z:ball_scale}}, "Ball")
+        }
+        //every pin has a model of its own, all instances of a model move together
+        Role::Pin(_) => {
+            let pin_width = variant.pin.radius / PIN_MODEL_RADIUS;
+            let pin_height = variant.pin.half_height / PIN_MODEL_HALF_HEIGHT;
+            let model = resources::load_model("pin.obj", device, queue).await.unwrap();
+            (model, Instance{position:Vector3{x: 0.0,y:-PIN_MODEL_CENTER * pin_height,z:0.0 },rotation:Quaternion::from_axis_angle(cgmath::Vector3::unit_y(), cgmath::Deg(0.0)),scale:Vector3{x:pin_width,y:pin_height,z:pin_width}}, "Pin")
+        }
+        Role::Lane => {
+            let model = resources::build_lane_model(&lane.blocks(), device, queue).await.unwrap();
+            (model, Instance{position:Vector3{x: 0.0,y:0.0,z:0.0},rotation:Quaternion::from_axis_angle(cgmath::Vector3::unit_y(), cgmath::Deg(0.0)),scale:Vector3{x:1.0,y:1.0,z:1.0}}, "Lane")
+        }
+        Role::Bowler => return None,
+    };
+    Some(Object::new(model, vec![instance], String::from(name)))
+}
+
+//the HUD of a headless run is what happened, printed once the game is over
+fn run_headless(mut run: headless::Headless, throws: Vec<phys::Throw>) {
+    let played = run.play(throws);
+    for event in run.drain_events() {
+        match event {
+            game::GameEvent::PhaseChanged(_) => {}
+            _ => println!("{event}"),
+        }
+    }
+    println!("{}", run.lineup());
+    if let Err(e) = played {
+        eprintln!("headless run: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn show_history(id: Option<&str>) {
+    let Some(history) = history::History::open() else {
+        eprintln!("no data directory to keep the game history in");
+        return;
+    };
+    let shown = match id {
+        Some(id) => history.load(id).map(|record| println!("{record}")),
+        None => history.list().map(|ids| {
+            for id in ids {
+                match history.load(&id) {
+                    Ok(record) => println!("{id}  {}", record.summary()),
+                    Err(e) => println!("{id}  {e}"),
+                }
+            }
+        }),
+    };
+    if let Err(e) = shown {
+        eprintln!("game history {}: {e}", history.dir().display());
+    }
+}
+
+fn delete_game(id: &str) {
+    let Some(history) = history::History::open() else {
+        eprintln!("no data directory to keep the game history in");
+        return;
+    };
+    match history.delete(id) {
+        Ok(()) => println!("Game {id} deleted"),
+        Err(e) => eprintln!("game {id}: {e}"),
+    }
+}
+
+fn load_physics_config(path: &std::path::Path) -> Result<physics_config::PhysicsConfig, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| physics_config::PhysicsConfig::parse(&text).map_err(|e| e.to_string()))
+        .map_err(|e| format!("physics config {}: {e}", path.display()))
+}
+
 pub fn run(title: &str) {
     env_logger::init();
+    //--oil=<name> picks res/patterns/<name>.txt, --variant=<ten|nine|candle|duck|five> the game,
+    //--practice=<leaves> throws at the given leaves instead, --league=<file> keeps the books
+    //and gives handicap, --tournament=<games>:<ladder|bracket>[:<finalists>] runs a tournament
+    //between the players, every other argument is a player.
+    //--history lists the saved games, --history=<id> shows one and --delete-game=<id> deletes it.
+    //--physics-rate=<Hz> sets how often physics is stepped. Without players the game left
+    //unfinished last time is resumed.
+    //--headless plays the game without a window, the bowlers at the keyboard throwing the throws
+    //in --throws=<file>, and --seed=<n> seeds the bots so the same run always comes out the same.
+    //--physics=<file> tunes physics, F5 reads the file again while the game runs
+    let (options, players): (Vec<String>, Vec<String>) = std::env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    let mut pattern_name = "house".to_string();
+    let mut variant = variant::Variant::default();
+    let mut practice_spec = None;
+    let mut league_path = None;
+    let mut format = None;
+    let mut physics_rate = PHYSICS_RATE;
+    let mut headless = false;
+    let mut seed = None;
+    let mut throws_path = None;
+    let mut physics_config_path = None;
+    for option in options {
+        if let Some(name) = option.strip_prefix("--oil=") {
+            pattern_name = name.to_string();
+        } else if let Some(kind) = option.strip_prefix("--variant=").and_then(variant::VariantKind::parse) {
+            variant = variant::Variant::new(kind);
+        } else if let Some(spec) = option.strip_prefix("--practice=") {
+            practice_spec = Some(spec.to_string());
+        } else if let Some(path) = option.strip_prefix("--league=") {
+            league_path = Some(std::path::PathBuf::from(path));
+        } else if let Some(rate) = option.strip_prefix("--physics-rate=") {
+            match rate.parse().ok().filter(|rate| (MIN_PHYSICS_RATE..=MAX_PHYSICS_RATE).contains(rate)) {
+                Some(rate) => physics_rate = rate,
+                None => {
+                    eprintln!("physics rate '{rate}' should be {MIN_PHYSICS_RATE} to {MAX_PHYSICS_RATE} Hz");
+                    return;
+                }
+            }
+        } else if let Some(path) = option.strip_prefix("--physics=") {
+            physics_config_path = Some(std::path::PathBuf::from(path));
+        } else if option == "--headless" {
+            headless = true;
+        } else if let Some(value) = option.strip_prefix("--seed=") {
+            match value.parse::<u64>() {
+                Ok(value) => seed = Some(value),
+                Err(_) => {
+                    eprintln!("seed '{value}' should be a whole number");
+                    return;
+                }
+            }
+        } else if let Some(path) = option.strip_prefix("--throws=") {
+            throws_path = Some(path.to_string());
+        } else if option == "--history" {
+            show_history(None);
+            return;
+        } else if let Some(id) = option.strip_prefix("--history=") {
+            show_history(Some(id));
+            return;
+        } else if let Some(id) = option.strip_prefix("--delete-game=") {
+            delete_game(id);
+            return;
+        } else if let Some(spec) = option.strip_prefix("--tournament=") {
+            match tournament::Format::parse(spec) {
+                Ok(spec) => format = Some(spec),
+                Err(e) => {
+                    eprintln!("{e}");
+                    return;
+                }
+            }
+        } else {
+            eprintln!("unknown option {option}");
+            return;
+        }
+    }
+    let physics_config = match physics_config_path.as_deref().map(load_physics_config).transpose() {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    //a game left unfinished is picked up again, unless the players for a new one are given
+    let save_path = save::path();
+    let saved = match &save_path {
+        Some(path) if players.is_empty() && practice_spec.is_none() && format.is_none() && !headless => {
+            match std::fs::read_to_string(path).map(|text| save::SavedGame::parse(&text)) {
+                Ok(Ok(saved)) => Some(saved),
+                Ok(Err(e)) => {
+                    eprintln!("saved game {}: {e}", path.display());
+                    None
+                }
+                Err(_) => None,
+            }
+        }
+        _ => None,
+    };
+    if let Some(saved) = &saved {
+        println!("Resuming the unfinished game, give the players to start a new one");
+        pattern_name = saved.oil.clone();
+        variant = variant::Variant::new(saved.variant);
+    }
+    let pattern = pollster::block_on(resources::load_string(&format!("patterns/{pattern_name}.txt")))
+        .map_err(|e| e.to_string())
+        .and_then(|text| oil::OilPattern::parse(&text).map_err(|e| e.to_string()));
+    let pattern = match pattern {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            eprintln!("oil pattern '{pattern_name}': {e}");
+            return;
+        }
+    };
+    //the leaves are checked against the rack of the chosen variant
+    let practice = match practice_spec.map(|spec| practice::Practice::parse(&spec, &variant)).transpose() {
+        Ok(practice) => practice,
+        Err(e) => {
+            eprintln!("practice: {e}");
+            return;
+        }
+    };
+    let mut lineup = match players::Lineup::from_args(players.into_iter()) {
+        Ok(lineup) => lineup,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    //a league file that doesn't exist yet starts new books
+    let league = match league_path {
+        Some(path) => {
+            let league = match std::fs::read_to_string(&path) {
+                Ok(text) => league::League::parse(&text),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(league::League::new()),
+                Err(e) => {
+                    eprintln!("league file {}: {e}", path.display());
+                    return;
+                }
+            };
+            match league {
+                Ok(league) => Some((path, league)),
+                Err(e) => {
+                    eprintln!("league file {}: {e}", path.display());
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+    if let Some((_, league)) = &league {
+        //handicap comes from the average bowlers bring into the session
+        for player in lineup.players_mut() {
+            player.handicap = Some(league.handicap(&player.name));
+        }
+        println!("{league}");
+    }
+    if practice.is_some() && format.is_some() {
+        eprintln!("practice doesn't score, it can't be part of a tournament");
+        return;
+    }
+    //the tournament hands out the lineup of every match, the first one included
+    let tournament = match format.map(|format| tournament::Tournament::new(lineup.players().to_vec(), format)) {
+        Some(Ok(mut tournament)) => {
+            if let Some(seed) = seed {
+                tournament.seed_bots(seed);
+            }
+            println!("{tournament}");
+            lineup = tournament.lineup().unwrap_or(lineup);
+            Some(tournament)
+        }
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            return;
+        }
+        None => None,
+    };
+    if headless {
+        if practice.is_some() || tournament.is_some() {
+            eprintln!("a headless run plays a single game, without practice or a tournament");
+            return;
+        }
+        let throws = match throws_path.map(|path| std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))) {
+            Some(Ok(text)) => headless::parse_throws(&text).map_err(|e| e.to_string()),
+            Some(Err(e)) => Err(e),
+            None => Ok(Vec::new()),
+        };
+        match throws {
+            Ok(throws) => {
+                let run = headless::Headless::new(lineup, variant, pattern, physics_config, physics_rate, seed.unwrap_or(0));
+                run_headless(run, throws);
+            }
+            Err(e) => eprintln!("throws: {e}"),
+        }
+        return;
+    }
+    if let Some(seed) = seed {
+        lineup.seed_bots(seed);
+    }
     let event_loop = EventLoop::new().unwrap();
     let window = winit::window::WindowBuilder::new().build(&event_loop).unwrap();
     window.set_title(title);
 
-    let mut state = pollster::block_on(State::new(&window));    
+    let mut state = pollster::block_on(State::new(&window, lineup, pattern, variant, practice));
+    state.league = league;
+    state.tournament = tournament;    
+    //sizes in the config build the pins again, the saved ones go on the deck after that
+    state.physics.set_config(physics_config);
+    if let Some(saved) = saved {
+        state.physics.restore_pins(&saved.pins);
+        match players::Lineup::resume(saved.players) {
+            Ok(lineup) => state.game.resume(lineup),
+            Err(e) => eprintln!("saved game: {e}"),
+        }
+    }
+    //practice has no game to save, a tournament is more than one
+    if state.game.practice().is_none() && state.tournament.is_none() {
+        state.autosave = save_path.map(|path| (path, pattern_name));
+    }
+    state.physics_config = physics_config_path;
+    state.physics.set_timestep(1.0 / physics_rate as f32);
+    let step = std::time::Duration::from_secs_f32(state.physics.timestep());
     let mut last_render_time: std::time::Instant = std::time::Instant::now();
-    let mut last_physics_sim: std::time::Instant = std::time::Instant::now();
-    let mut dtphysics = std::time::Instant::now() - last_physics_sim;
+    //time the physics still has to step through
+    let mut accumulator = std::time::Duration::ZERO;
 
     event_loop.run(move |event, control_flow: &winit::event_loop::EventLoopWindowTarget<()>| {
         match event {
@@ -630,14 +1195,14 @@ pub fn run(title: &str) {
                         WindowEvent::RedrawRequested => {
                             let now = std::time::Instant::now();
                             let dt = now - last_render_time;
-                            if(dtphysics.as_secs_f32()> 0.0001){
+                            last_render_time = now;
+                            //physics runs as many fixed steps as fit in the time since the last frame
+                            accumulator += dt.min(MAX_FRAME_TIME);
+                            while accumulator >= step {
                                 state.fixed_update();
-                                last_physics_sim = now;
-                            } else{
-                                dtphysics = now - last_physics_sim;
+                                accumulator -= step;
                             }
-                            last_render_time = now;
-                            state.update(dt);
+                            state.update(dt, accumulator.as_secs_f32() / step.as_secs_f32());
                             
                             
                             state.window().request_redraw();
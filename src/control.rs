@@ -3,8 +3,8 @@ use rapier3d::na::vector;
 use winit::keyboard::KeyCode;
 use winit::event::*;
 
-use super::{camera::{ Camera, CameraController}, phys::Physics};
-pub fn process_keyboard(key: KeyCode, state: ElementState, camera_controller:&mut CameraController,physics: &mut Physics,camera: &Camera) -> bool {
+use super::{camera::CameraController, phys::Physics, game::Game, delivery::{Delivery, DeliveryInput, DeliveryStage}};
+pub fn process_keyboard(key: KeyCode, state: ElementState, camera_controller:&mut CameraController,physics: &mut Physics, game: &mut Game, delivery: &mut Delivery) -> bool {
     let amount = if state == ElementState::Pressed {
         1.0
     } else {
@@ -35,9 +35,41 @@ pub fn process_keyboard(key: KeyCode, state: ElementState, camera_controller:&mu
             camera_controller.down(amount);
             true
         }
-        KeyCode::KeyE =>{
-            
-            physics.throw_ball(camera.look_direction());
+        KeyCode::KeyJ | KeyCode::KeyL | KeyCode::KeyE | KeyCode::KeyQ =>{
+            //the throw sequence only runs while the bowler is up
+            if state == ElementState::Pressed && game.can_throw() {
+                let input = match key {
+                    KeyCode::KeyJ => DeliveryInput::Left,
+                    KeyCode::KeyL => DeliveryInput::Right,
+                    KeyCode::KeyE => DeliveryInput::Confirm,
+                    _ => DeliveryInput::Back,
+                };
+                match delivery.input(input) {
+                    Some(throw) => {
+                        println!();
+                        game.throw(physics, throw);
+                    }
+                    None => {
+                        if delivery.stage() == DeliveryStage::Stance {
+                            physics.place_ball(delivery.stance_x());
+                        }
+                        //overwrites the meter if it was running
+                        println!("\r{:<22}", delivery.to_string());
+                    }
+                }
+            }
+            true
+        }
+        KeyCode::KeyN =>{
+            if state == ElementState::Pressed {
+                game.new_game();
+            }
+            true
+        }
+        KeyCode::KeyP =>{
+            if state == ElementState::Pressed {
+                game.next_leave(physics);
+            }
             true
         }
         KeyCode::KeyV =>{
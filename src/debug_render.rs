@@ -0,0 +1,104 @@
+use nalgebra::Scale;
+use rapier3d::pipeline::{DebugRenderBackend, DebugRenderMode, DebugRenderObject, DebugRenderPipeline, DebugRenderStyle};
+use rapier3d::prelude::{Isometry, Point, Real, Vector};
+
+use super::phys::Physics;
+use super::resources::model::Vertex;
+
+// What physics sees, drawn over the scene as lines: the collider shapes (dimmed while their body
+// sleeps), the contact points with their normals, and how fast every awake body is going
+const MODE: DebugRenderMode = DebugRenderMode::COLLIDER_SHAPES.union(DebugRenderMode::CONTACTS);
+// metres of line for every m/s
+pub const VELOCITY_SCALE: f32 = 0.1;
+// HSLA like rapier's own colours
+pub const VELOCITY_COLOR: [f32; 4] = [60.0, 1.0, 0.5, 1.0];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl Vertex for LineVertex {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute { offset: 0, shader_location: 0, format: wgpu::VertexFormat::Float32x3 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+// Collects the lines of one frame, two vertices a line
+pub struct DebugLines {
+    pipeline: DebugRenderPipeline,
+    pub vertices: Vec<LineVertex>,
+}
+
+impl DebugLines {
+    pub fn new() -> Self {
+        Self { pipeline: DebugRenderPipeline::new(DebugRenderStyle::default(), MODE), vertices: Vec::new() }
+    }
+
+    pub fn collect(&mut self, physics: &Physics) {
+        self.vertices.clear();
+        let mut backend = Backend { vertices: &mut self.vertices };
+        physics.debug_render(&mut self.pipeline, &mut backend);
+    }
+}
+
+struct Backend<'a> {
+    vertices: &'a mut Vec<LineVertex>,
+}
+
+impl DebugRenderBackend for Backend<'_> {
+    fn draw_line(&mut self, _object: DebugRenderObject, a: Point<Real>, b: Point<Real>, color: [f32; 4]) {
+        let color = hsla_to_rgba(color);
+        self.vertices.push(LineVertex { position: a.coords.into(), color });
+        self.vertices.push(LineVertex { position: b.coords.into(), color });
+    }
+
+    // parry's outline of a rounded convex hull can index past its own vertices, those edges are left out
+    fn draw_polyline(
+        &mut self,
+        object: DebugRenderObject,
+        vertices: &[Point<Real>],
+        indices: &[[u32; 2]],
+        transform: &Isometry<Real>,
+        scale: &Vector<Real>,
+        color: [f32; 4],
+    ) {
+        let point = |index: u32| vertices.get(index as usize).map(|vertex| transform * (Scale::from(*scale) * vertex));
+        for &[a, b] in indices {
+            if let (Some(a), Some(b)) = (point(a), point(b)) {
+                self.draw_line(object, a, b, color);
+            }
+        }
+    }
+}
+
+// hue in degrees, the rest from 0 to 1
+fn hsla_to_rgba([hue, saturation, lightness, alpha]: [f32; 4]) -> [f32; 4] {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    [r + m, g + m, b + m, alpha]
+}
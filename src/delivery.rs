@@ -0,0 +1,228 @@
+use std::fmt;
+
+use super::lane::{LaneSpec, BOARDS};
+use super::phys::Throw;
+
+// Target arrows are painted 15 ft past the foul line, on every fifth board
+const ARROW_DISTANCE: f32 = 4.572;
+const ARROWS: [u8; 7] = [5, 10, 15, 20, 25, 30, 35];
+// seconds for the meter to sweep up and back down
+const METER_PERIOD: f32 = 1.5;
+// release speeds at the bottom and top of the power meter, m/s
+const MIN_SPEED: f32 = 6.0;
+const MAX_SPEED: f32 = 10.0;
+// axis rotation at either end of the spin meter, the middle is a straight ball
+const MAX_AXIS_ROTATION: f32 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryInput {
+    Left,
+    Right,
+    Confirm,
+    Back,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStage {
+    // picking the board to stand on
+    Stance,
+    // picking the arrow to roll over
+    Aim,
+    // the power meter is running
+    Power,
+    // the spin meter is running
+    Spin,
+}
+
+// The throw sequence: stance, aim, then power and spin timed on an oscillating meter.
+// It only sees abstract inputs and elapsed time, the caller maps keys onto it.
+pub struct Delivery {
+    lane: LaneSpec,
+    stage: DeliveryStage,
+    board: u8,
+    arrow: usize,
+    meter_time: f32,
+    power: f32,
+}
+
+impl Delivery {
+    pub fn new(lane: LaneSpec) -> Self {
+        Self {
+            lane,
+            stage: DeliveryStage::Stance,
+            board: ARROWS[3],
+            arrow: 3,
+            meter_time: 0.0,
+            power: 0.0,
+        }
+    }
+
+    pub fn stage(&self) -> DeliveryStage {
+        self.stage
+    }
+
+    // where the ball sits on the approach
+    pub fn stance_x(&self) -> f32 {
+        self.lane.board_x(self.board)
+    }
+
+    // 0 to 1 and back, over and over
+    pub fn meter(&self) -> f32 {
+        let phase = (self.meter_time / METER_PERIOD).fract();
+        1.0 - (2.0 * phase - 1.0).abs()
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if matches!(self.stage, DeliveryStage::Power | DeliveryStage::Spin) {
+            self.meter_time += dt;
+        }
+    }
+
+    // Returns the throw once the spin has been locked in
+    pub fn input(&mut self, input: DeliveryInput) -> Option<Throw> {
+        match (self.stage, input) {
+            // boards are counted from the right, so left is up
+            (DeliveryStage::Stance, DeliveryInput::Left) => self.board = (self.board + 1).min(BOARDS as u8),
+            (DeliveryStage::Stance, DeliveryInput::Right) => self.board = (self.board - 1).max(1),
+            (DeliveryStage::Aim, DeliveryInput::Left) => self.arrow = (self.arrow + 1).min(ARROWS.len() - 1),
+            (DeliveryStage::Aim, DeliveryInput::Right) => self.arrow = self.arrow.saturating_sub(1),
+            (DeliveryStage::Stance, DeliveryInput::Confirm) => self.stage = DeliveryStage::Aim,
+            (DeliveryStage::Aim, DeliveryInput::Confirm) => self.start_meter(DeliveryStage::Power),
+            (DeliveryStage::Power, DeliveryInput::Confirm) => {
+                self.power = self.meter();
+                self.start_meter(DeliveryStage::Spin);
+            }
+            (DeliveryStage::Spin, DeliveryInput::Confirm) => {
+                let spin = self.meter() * 2.0 - 1.0;
+                //the bowler keeps the stance and target for the next ball
+                self.stage = DeliveryStage::Stance;
+                return Some(self.release(spin));
+            }
+            (DeliveryStage::Aim, DeliveryInput::Back) => self.stage = DeliveryStage::Stance,
+            (DeliveryStage::Power, DeliveryInput::Back) => self.stage = DeliveryStage::Aim,
+            (DeliveryStage::Spin, DeliveryInput::Back) => self.start_meter(DeliveryStage::Power),
+            _ => {}
+        }
+        None
+    }
+
+    fn start_meter(&mut self, stage: DeliveryStage) {
+        self.stage = stage;
+        self.meter_time = 0.0;
+    }
+
+    fn release(&self, spin: f32) -> Throw {
+        let position = self.stance_x();
+        let target = self.lane.board_x(ARROWS[self.arrow]);
+        Throw {
+            position,
+            speed: MIN_SPEED + (MAX_SPEED - MIN_SPEED) * self.power,
+            launch_angle: (target - position).atan2(ARROW_DISTANCE).to_degrees(),
+            axis_rotation: spin * MAX_AXIS_ROTATION,
+            ..Throw::default()
+        }
+    }
+}
+
+impl fmt::Display for Delivery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.stage {
+            DeliveryStage::Stance => write!(f, "Stance: board {}", self.board),
+            DeliveryStage::Aim => write!(f, "Aim: arrow {} (board {})", self.arrow + 1, ARROWS[self.arrow]),
+            DeliveryStage::Power => write!(f, "Power: time the meter"),
+            DeliveryStage::Spin => write!(f, "Spin: power {:.0}%, time the meter", self.power * 100.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivery() -> Delivery {
+        Delivery::new(LaneSpec::REGULATION)
+    }
+
+    fn press(delivery: &mut Delivery, inputs: &[DeliveryInput]) -> Option<Throw> {
+        inputs.iter().fold(None, |_, &input| delivery.input(input))
+    }
+
+    #[test]
+    fn stance_stays_on_the_lane() {
+        let mut delivery = delivery();
+        press(&mut delivery, &[DeliveryInput::Left; 60]);
+        assert_eq!(delivery.board, BOARDS as u8);
+        press(&mut delivery, &[DeliveryInput::Right; 60]);
+        assert_eq!(delivery.board, 1);
+        assert_eq!(delivery.stance_x(), LaneSpec::REGULATION.board_x(1));
+    }
+
+    #[test]
+    fn aim_stays_on_the_arrows() {
+        let mut delivery = delivery();
+        press(&mut delivery, &[DeliveryInput::Confirm]);
+        assert_eq!(delivery.stage(), DeliveryStage::Aim);
+        press(&mut delivery, &[DeliveryInput::Left; 10]);
+        assert_eq!(delivery.arrow, ARROWS.len() - 1);
+        press(&mut delivery, &[DeliveryInput::Right; 10]);
+        assert_eq!(delivery.arrow, 0);
+        // left and right move the aim, not the stance
+        assert_eq!(delivery.board, ARROWS[3]);
+    }
+
+    #[test]
+    fn meter_sweeps_up_and_down() {
+        let mut delivery = delivery();
+        delivery.update(METER_PERIOD / 4.0);
+        // it only runs while a meter is up
+        assert_eq!(delivery.meter(), 0.0);
+        press(&mut delivery, &[DeliveryInput::Confirm, DeliveryInput::Confirm]);
+        assert_eq!(delivery.stage(), DeliveryStage::Power);
+        for (time, meter) in [(0.25, 0.5), (0.25, 1.0), (0.25, 0.5), (0.25, 0.0), (0.125, 0.25)] {
+            delivery.update(METER_PERIOD * time);
+            assert!((delivery.meter() - meter).abs() < 1e-4, "{} instead of {meter}", delivery.meter());
+        }
+    }
+
+    #[test]
+    fn back_goes_one_stage_back() {
+        let mut delivery = delivery();
+        press(&mut delivery, &[DeliveryInput::Back]);
+        assert_eq!(delivery.stage(), DeliveryStage::Stance);
+        press(&mut delivery, &[DeliveryInput::Confirm, DeliveryInput::Confirm, DeliveryInput::Confirm]);
+        assert_eq!(delivery.stage(), DeliveryStage::Spin);
+        // the power meter starts over
+        delivery.update(METER_PERIOD / 4.0);
+        press(&mut delivery, &[DeliveryInput::Back]);
+        assert_eq!((delivery.stage(), delivery.meter()), (DeliveryStage::Power, 0.0));
+        press(&mut delivery, &[DeliveryInput::Back]);
+        assert_eq!(delivery.stage(), DeliveryStage::Aim);
+        press(&mut delivery, &[DeliveryInput::Back]);
+        assert_eq!(delivery.stage(), DeliveryStage::Stance);
+        // left and right go nowhere while a meter runs
+        press(&mut delivery, &[DeliveryInput::Confirm, DeliveryInput::Confirm, DeliveryInput::Left]);
+        assert_eq!((delivery.board, delivery.arrow), (ARROWS[3], 3));
+    }
+
+    #[test]
+    fn timed_meters_make_the_throw() {
+        let lane = LaneSpec::REGULATION;
+        let mut delivery = delivery();
+        // board 10 to the arrow on board 15
+        press(&mut delivery, &[DeliveryInput::Right; 10]);
+        press(&mut delivery, &[DeliveryInput::Confirm, DeliveryInput::Right, DeliveryInput::Confirm]);
+        // full power, then a quarter of the way up the spin meter
+        delivery.update(METER_PERIOD / 2.0);
+        assert_eq!(press(&mut delivery, &[DeliveryInput::Confirm]), None);
+        delivery.update(METER_PERIOD / 8.0);
+        let throw = press(&mut delivery, &[DeliveryInput::Confirm]).unwrap();
+        assert_eq!(throw.position, lane.board_x(10));
+        assert!((throw.speed - MAX_SPEED).abs() < 1e-4);
+        let angle = (5.0 * lane.board_width()).atan2(ARROW_DISTANCE).to_degrees();
+        assert!((throw.launch_angle - angle).abs() < 1e-4);
+        assert!((throw.axis_rotation + MAX_AXIS_ROTATION / 2.0).abs() < 1e-3);
+        // the next ball starts from the same stance and aim
+        assert_eq!(delivery.stage(), DeliveryStage::Stance);
+        assert_eq!((delivery.board, ARROWS[delivery.arrow]), (10, 15));
+    }
+}
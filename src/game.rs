@@ -0,0 +1,569 @@
+use std::cell::Cell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use super::leave::Leave;
+use super::phys::{LaneEvent, Physics, PinSet, Throw};
+use super::pinsetter::Pinsetter;
+use super::practice::{Attempts, Practice};
+use super::scoring::ScoreError;
+use super::variant::{Variant, VariantKind};
+use super::players::Lineup;
+
+//longest a ball may roll before it is treated as having reached the pit
+const ROLL_TIMEOUT: Duration = Duration::from_secs(8);
+//how long the result of a ball stays up before the next throw
+const SCORING_DISPLAY: Duration = Duration::from_millis(1500);
+//computer bowlers take a moment on the approach before they throw
+const BOT_SETUP: Duration = Duration::from_millis(1000);
+
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+// Clock that only moves when it is told to, so the game flow can run headless
+#[derive(Default)]
+pub struct ManualClock {
+    now: Cell<Duration>,
+}
+
+impl ManualClock {
+    pub fn advance(&self, dt: Duration) {
+        self.now.set(self.now.get() + dt);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamePhase {
+    Aiming,
+    BallRolling,
+    PinsSettling,
+    Scoring,
+    GameOver,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    PhaseChanged(GamePhase),
+    BallThrown,
+    BallScored { frame: usize, knocked: u8, standing: PinSet },
+    GutterBall,
+    Foul,
+    Leave(Leave),
+    Strike,
+    Spare,
+    FrameComplete { frame: usize, total: u16 },
+    PlayerUp { name: String },
+    GameOver { winner: String, total: u16 },
+    PracticeLeave(PinSet),
+    PracticeBall { leave: PinSet, standing: PinSet, attempts: Attempts },
+    //the score sheet refused the ball, it is thrown again at the same rack
+    BallNotScored(ScoreError),
+}
+
+impl fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameEvent::PhaseChanged(phase) => write!(f, "{phase:?}"),
+            GameEvent::BallThrown => write!(f, "Ball thrown"),
+            GameEvent::BallScored { frame, knocked, standing } => {
+                write!(f, "Frame {}: {knocked} pins down, standing: {standing}", frame + 1)
+            }
+            GameEvent::GutterBall => write!(f, "Gutter ball"),
+            GameEvent::Foul => write!(f, "Foul!"),
+            GameEvent::Leave(leave) => write!(f, "Leave: {leave}"),
+            GameEvent::Strike => write!(f, "Strike!"),
+            GameEvent::Spare => write!(f, "Spare!"),
+            GameEvent::FrameComplete { frame, total } => write!(f, "Frame {} complete, total {total}", frame + 1),
+            GameEvent::PlayerUp { name } => write!(f, "{name} is up"),
+            GameEvent::GameOver { winner, total } => write!(f, "Game over, {winner} wins with {total}"),
+            GameEvent::PracticeLeave(leave) => write!(f, "Practice leave: {leave}"),
+            GameEvent::BallNotScored(error) => write!(f, "Ball not scored: {error}"),
+            GameEvent::PracticeBall { leave, standing, attempts } => {
+                if standing.len() == 0 {
+                    write!(f, "Converted {leave}, {attempts}")
+                } else {
+                    write!(f, "Missed {leave}, standing: {standing}, {attempts}")
+                }
+            }
+        }
+    }
+}
+
+// Game flow: owns the players' scores and the pinsetter, and decides when a throw is allowed.
+// Physics is stepped by the caller, the game only reacts to it.
+pub struct Game<C: Clock = SystemClock> {
+    clock: C,
+    phase: GamePhase,
+    phase_start: Duration,
+    variant: Variant,
+    lineup: Lineup,
+    pinsetter: Pinsetter,
+    events: Vec<GameEvent>,
+    //pins on the deck when the ball was released, respotted if the ball doesn't count
+    rack: PinSet,
+    //set when the current ball is a gutter ball or a foul
+    dead_ball: Option<LaneEvent>,
+    //spare practice replaces the score sheet when set
+    practice: Option<Practice>,
+}
+
+impl<C: Clock> Game<C> {
+    pub fn new(clock: C, mut lineup: Lineup, variant: Variant) -> Self {
+        let phase_start = clock.now();
+        lineup.reset(variant.rules);
+        Self {
+            clock,
+            phase: GamePhase::Aiming,
+            phase_start,
+            lineup,
+            pinsetter: Pinsetter::new(!variant.deadwood_stays),
+            variant,
+            events: Vec::new(),
+            rack: PinSet::default(),
+            dead_ball: None,
+            practice: None,
+        }
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    pub fn phase(&self) -> GamePhase {
+        self.phase
+    }
+
+    pub fn lineup(&self) -> &Lineup {
+        &self.lineup
+    }
+
+    pub fn variant(&self) -> &Variant {
+        &self.variant
+    }
+
+    pub fn practice(&self) -> Option<&Practice> {
+        self.practice.as_ref()
+    }
+
+    // Nothing is scored from here on, every ball is thrown at the practice leave
+    pub fn start_practice(&mut self, physics: &mut Physics, practice: Practice) {
+        physics.set_up_leave(practice.leave());
+        self.events.push(GameEvent::PracticeLeave(practice.leave()));
+        self.practice = Some(practice);
+    }
+
+    // Moves on to the next practice leave between balls
+    pub fn next_leave(&mut self, physics: &mut Physics) {
+        if !self.rack_is_set() {
+            return;
+        }
+        if let Some(practice) = &mut self.practice {
+            let leave = practice.next_leave();
+            self.pinsetter.respot(physics, leave, false);
+            self.events.push(GameEvent::PracticeLeave(leave));
+        }
+    }
+
+    fn rack_is_set(&self) -> bool {
+        self.phase == GamePhase::Aiming && !self.pinsetter.is_busy()
+    }
+
+    fn bot_is_up(&self) -> bool {
+        self.lineup.bowler().bot.is_some()
+    }
+
+    // Whether the bowler at the keyboard may throw
+    pub fn can_throw(&self) -> bool {
+        self.rack_is_set() && !self.bot_is_up()
+    }
+
+    // Throws are ignored unless the bowler is on the approach with a set rack
+    pub fn throw(&mut self, physics: &mut Physics, throw: Throw) -> bool {
+        if !self.can_throw() {
+            return false;
+        }
+        self.release(physics, throw);
+        true
+    }
+
+    fn release(&mut self, physics: &mut Physics, throw: Throw) {
+        //small-ball games have one ball for everybody
+        let weight_lb = self.variant.ball.weight_lb.unwrap_or(self.lineup.bowler().ball.weight_lb as f32);
+        physics.set_ball_weight(weight_lb);
+        physics.throw_ball(&throw);
+        self.rack = physics.standing_pins();
+        //the camera is the human bowler, a bot never crosses the line
+        self.dead_ball = (!self.bot_is_up() && physics.bowler_over_foul_line()).then_some(LaneEvent::Foul);
+        self.events.push(GameEvent::BallThrown);
+        self.enter(GamePhase::BallRolling);
+    }
+
+    pub fn new_game(&mut self) {
+        if self.phase == GamePhase::GameOver {
+            self.start();
+        }
+    }
+
+    // The next match of a tournament, bowled by a different lineup
+    pub fn start_match(&mut self, lineup: Lineup) {
+        self.lineup = lineup;
+        self.start();
+    }
+
+    // Picks a saved game up where it stopped, its pins have to be back on the deck already
+    pub fn resume(&mut self, lineup: Lineup) {
+        self.lineup = lineup;
+        self.events.push(GameEvent::PlayerUp { name: self.lineup.bowler_name() });
+        self.enter(GamePhase::Aiming);
+    }
+
+    fn start(&mut self) {
+        self.lineup.reset(self.variant.rules);
+        self.events.push(GameEvent::PlayerUp { name: self.lineup.bowler_name() });
+        self.enter(GamePhase::Aiming);
+    }
+
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn enter(&mut self, phase: GamePhase) {
+        self.phase = phase;
+        self.phase_start = self.clock.now();
+        self.events.push(GameEvent::PhaseChanged(phase));
+    }
+
+    // Called after every physics step
+    pub fn update(&mut self, physics: &mut Physics) {
+        let dt = physics.timestep();
+        self.pinsetter.update(physics, dt);
+        let elapsed = self.clock.now().saturating_sub(self.phase_start);
+        for event in physics.drain_lane_events() {
+            //a foul counts until the ball is scored, the gutter only until the ball reaches the pins
+            let counts = match event {
+                LaneEvent::Foul => {
+                    !self.bot_is_up() && matches!(self.phase, GamePhase::BallRolling | GamePhase::PinsSettling)
+                }
+                LaneEvent::GutterBall => self.phase == GamePhase::BallRolling,
+            };
+            if counts && self.dead_ball.is_none() {
+                self.dead_ball = Some(event);
+            }
+        }
+        match self.phase {
+            GamePhase::Aiming => {
+                if self.rack_is_set() && elapsed >= BOT_SETUP {
+                    let standing = physics.standing_pins();
+                    if let Some(bot) = &mut self.lineup.bowler_mut().bot {
+                        let throw = bot.throw(physics, standing);
+                        self.release(physics, throw);
+                    }
+                }
+            }
+            GamePhase::GameOver => {}
+            GamePhase::BallRolling => {
+                if physics.pins_in_motion() || physics.is_settled() || elapsed > ROLL_TIMEOUT {
+                    self.enter(GamePhase::PinsSettling);
+                }
+            }
+            GamePhase::PinsSettling => {
+                if physics.fallen_pins().is_some() {
+                    self.score_ball(physics);
+                    self.enter(GamePhase::Scoring);
+                }
+            }
+            GamePhase::Scoring => {
+                if !self.pinsetter.is_busy() && elapsed >= SCORING_DISPLAY {
+                    if self.lineup.is_complete() {
+                        let leader = self.lineup.leader();
+                        self.events.push(GameEvent::GameOver { winner: leader.name.clone(), total: leader.total_with_handicap() });
+                        self.enter(GamePhase::GameOver);
+                    } else {
+                        self.enter(GamePhase::Aiming);
+                    }
+                }
+            }
+        }
+    }
+
+    fn score_ball(&mut self, physics: &mut Physics) {
+        physics.finish_throw();
+        let dead_ball = self.dead_ball.take();
+        //a dead ball scores nothing and leaves the rack as it was
+        let standing = if dead_ball.is_some() { self.rack } else { physics.standing_pins() };
+        if let Some(bot) = &mut self.lineup.bowler_mut().bot {
+            bot.observe(physics);
+        }
+        match dead_ball {
+            Some(LaneEvent::GutterBall) => self.events.push(GameEvent::GutterBall),
+            Some(LaneEvent::Foul) => self.events.push(GameEvent::Foul),
+            None => {}
+        }
+        if let Some(practice) = &mut self.practice {
+            let attempts = practice.record(standing);
+            let leave = practice.leave();
+            self.events.push(GameEvent::PracticeBall { leave, standing, attempts });
+            //the same leave is set up again for the next ball
+            self.pinsetter.respot(physics, leave, false);
+            return;
+        }
+        let score = &mut self.lineup.active_mut().score;
+        let first_ball = score.first_ball_at_rack();
+        //five-pin scores the points on the pins, the other games count them
+        let rerack = match score.record_standing(self.variant.value_of(standing)) {
+            Ok(result) => {
+                self.events.push(GameEvent::BallScored { frame: result.frame, knocked: result.knocked, standing });
+                score.mark_leave(standing);
+                //leaves only count after the first ball at a ten-pin rack, not after a second ball that found it untouched
+                if self.variant.kind == VariantKind::TenPin && dead_ball.is_none() && first_ball && standing.len() > 0 {
+                    let leave = Leave::classify(standing);
+                    if leave.split {
+                        score.mark_split();
+                    }
+                    if leave.split || leave.name.is_some() {
+                        self.events.push(GameEvent::Leave(leave));
+                    }
+                }
+                if result.strike {
+                    self.events.push(GameEvent::Strike);
+                } else if result.spare {
+                    self.events.push(GameEvent::Spare);
+                }
+                if result.frame_complete {
+                    self.events.push(GameEvent::FrameComplete { frame: result.frame, total: score.total() });
+                    //bowlers take turns frame by frame
+                    self.lineup.next_turn();
+                    if !self.lineup.is_complete() {
+                        self.events.push(GameEvent::PlayerUp { name: self.lineup.bowler_name() });
+                    }
+                }
+                result.rerack
+            }
+            Err(error) => {
+                log::warn!("ball not scored: {error}");
+                self.events.push(GameEvent::BallNotScored(error));
+                self.pinsetter.respot(physics, self.rack, false);
+                return;
+            }
+        };
+        if dead_ball.is_some() {
+            self.pinsetter.respot(physics, standing, rerack);
+        } else {
+            self.pinsetter.start_cycle(physics, rerack);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lane::LaneSpec;
+    use super::*;
+
+    // straight at the head pin, the rack never stays untouched
+    const POCKET: Throw = Throw { position: 0.0, speed: 8.0, launch_angle: 0.0, rev_rate: 0.0, axis_rotation: 0.0, axis_tilt: 0.0 };
+    // straight down the left of the head pin, leaves the 1-3-6-10 picket fence
+    const PICKET_FENCE: Throw = Throw { position: 0.27, ..POCKET };
+    // straight into the left gutter
+    const GUTTER: Throw = Throw { position: 0.5, speed: 8.0, launch_angle: 2.0, rev_rate: 0.0, axis_rotation: 0.0, axis_tilt: 0.0 };
+
+    fn setup() -> (Physics, Game<ManualClock>) {
+        let mut physics = Physics::new(LaneSpec::REGULATION);
+        physics.build_colliders();
+        let lineup = Lineup::from_args(std::iter::empty()).unwrap();
+        let mut game = Game::new(ManualClock::default(), lineup, Variant::default());
+        // a new rack wobbles for a moment, the first ball of a real game comes long after that
+        for _ in 0..180 {
+            step(&mut physics, &mut game);
+        }
+        game.drain_events();
+        (physics, game)
+    }
+
+    fn step(physics: &mut Physics, game: &mut Game<ManualClock>) {
+        physics.simulate();
+        game.clock().advance(Duration::from_secs_f32(physics.timestep()));
+        game.update(physics);
+    }
+
+    // Steps until the bowler may throw again, gives back what the game reported on the way
+    fn bowl(physics: &mut Physics, game: &mut Game<ManualClock>, throw: Throw) -> Vec<GameEvent> {
+        assert!(game.throw(physics, throw));
+        let mut events = game.drain_events();
+        for _ in 0..60 * 60 {
+            step(physics, game);
+            events.extend(game.drain_events());
+            if game.can_throw() {
+                return events;
+            }
+        }
+        panic!("the ball was never done: {events:?}");
+    }
+
+    fn phases(events: &[GameEvent]) -> Vec<GamePhase> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::PhaseChanged(phase) => Some(*phase),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ball_goes_through_every_phase() {
+        let (mut physics, mut game) = setup();
+        assert_eq!(game.phase(), GamePhase::Aiming);
+        let events = bowl(&mut physics, &mut game, POCKET);
+        assert_eq!(
+            phases(&events),
+            [GamePhase::BallRolling, GamePhase::PinsSettling, GamePhase::Scoring, GamePhase::Aiming]
+        );
+    }
+
+    #[test]
+    fn no_throw_while_the_ball_rolls() {
+        let (mut physics, mut game) = setup();
+        assert!(game.throw(&mut physics, POCKET));
+        assert!(!game.can_throw());
+        assert!(!game.throw(&mut physics, POCKET));
+        while game.phase() != GamePhase::Scoring {
+            step(&mut physics, &mut game);
+            assert!(!game.throw(&mut physics, POCKET));
+        }
+        let thrown = game.drain_events().iter().filter(|event| **event == GameEvent::BallThrown).count();
+        assert_eq!(thrown, 1);
+    }
+
+    #[test]
+    fn events_come_in_order() {
+        let (mut physics, mut game) = setup();
+        let events = bowl(&mut physics, &mut game, GUTTER);
+        assert_eq!(
+            events,
+            [
+                GameEvent::BallThrown,
+                GameEvent::PhaseChanged(GamePhase::BallRolling),
+                GameEvent::PhaseChanged(GamePhase::PinsSettling),
+                GameEvent::GutterBall,
+                GameEvent::BallScored { frame: 0, knocked: 0, standing: physics.all_pins() },
+                GameEvent::PhaseChanged(GamePhase::Scoring),
+                GameEvent::PhaseChanged(GamePhase::Aiming),
+            ]
+        );
+        let events = bowl(&mut physics, &mut game, GUTTER);
+        assert_eq!(
+            events[3..6],
+            [
+                GameEvent::GutterBall,
+                GameEvent::BallScored { frame: 0, knocked: 0, standing: physics.all_pins() },
+                GameEvent::FrameComplete { frame: 0, total: 0 },
+            ]
+        );
+        assert_eq!(events[6], GameEvent::PlayerUp { name: "Player 1".to_string() });
+    }
+
+    fn leaves(events: &[GameEvent]) -> Vec<PinSet> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::Leave(leave) => Some(leave.pins),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn leave_of_the_first_ball() {
+        let (mut physics, mut game) = setup();
+        let events = bowl(&mut physics, &mut game, PICKET_FENCE);
+        assert_eq!(leaves(&events), [physics.standing_pins()]);
+    }
+
+    #[test]
+    fn no_leave_after_a_first_ball_miss() {
+        let (mut physics, mut game) = setup();
+        bowl(&mut physics, &mut game, GUTTER);
+        let events = bowl(&mut physics, &mut game, PICKET_FENCE);
+        assert!(events.iter().any(|event| matches!(event, GameEvent::BallScored { knocked: 1.., .. })), "{events:?}");
+        assert_eq!(leaves(&events), []);
+        assert!(game.lineup().active().score.frames()[0].splits.is_empty());
+    }
+
+    #[test]
+    fn refused_ball_is_thrown_again() {
+        let (mut physics, mut game) = setup();
+        bowl(&mut physics, &mut game, POCKET);
+        // the sheet has pins down, a full rack on the deck can't be scored against it
+        physics.rerack();
+        let events = bowl(&mut physics, &mut game, GUTTER);
+        assert!(events.iter().any(|event| matches!(event, GameEvent::BallNotScored(ScoreError::TooManyPins { .. }))));
+        assert!(!events.iter().any(|event| matches!(event, GameEvent::BallScored { .. })));
+        let score = &game.lineup().active().score;
+        assert_eq!((score.current_frame(), score.frames()[0].rolls.len()), (0, 1));
+        assert_eq!(physics.standing_pins(), physics.all_pins());
+    }
+
+    fn pins(numbers: &[u8]) -> PinSet {
+        let mut pins = PinSet::default();
+        for &pin in numbers {
+            pins.insert(pin);
+        }
+        pins
+    }
+
+    #[test]
+    fn practice_sets_the_leave_up_again() {
+        let (mut physics, mut game) = setup();
+        let practice = Practice::parse("10,4-7-10", game.variant()).unwrap();
+        game.start_practice(&mut physics, practice);
+        assert_eq!(game.drain_events(), [GameEvent::PracticeLeave(pins(&[10]))]);
+        assert_eq!(physics.standing_pins(), pins(&[10]));
+        let events = bowl(&mut physics, &mut game, GUTTER);
+        let attempts = Attempts { thrown: 1, converted: 0 };
+        assert!(events.contains(&GameEvent::PracticeBall { leave: pins(&[10]), standing: pins(&[10]), attempts }));
+        // nothing goes on the score sheet
+        assert!(!events.iter().any(|event| matches!(event, GameEvent::BallScored { .. })));
+        assert!(game.lineup().active().score.frames()[0].rolls.is_empty());
+        // straight down the right edge of the deck takes out the 10
+        let events = bowl(&mut physics, &mut game, Throw { position: -0.457, ..POCKET });
+        let attempts = Attempts { thrown: 2, converted: 1 };
+        assert!(events.contains(&GameEvent::PracticeBall { leave: pins(&[10]), standing: PinSet::default(), attempts }));
+        assert_eq!(physics.standing_pins(), pins(&[10]));
+        // the next leave comes up once the bowler asks for it
+        game.next_leave(&mut physics);
+        assert_eq!(game.drain_events(), [GameEvent::PracticeLeave(pins(&[4, 7, 10]))]);
+        while !game.can_throw() {
+            step(&mut physics, &mut game);
+        }
+        assert_eq!(physics.standing_pins(), pins(&[4, 7, 10]));
+    }
+}
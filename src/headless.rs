@@ -0,0 +1,208 @@
+use std::fmt;
+use std::time::Duration;
+
+use super::game::{Game, GameEvent, GamePhase, ManualClock};
+use super::lane::LaneSpec;
+use super::oil::OilPattern;
+use super::phys::{Physics, PinSet, Throw};
+use super::physics_config::PhysicsConfig;
+use super::players::Lineup;
+use super::variant::Variant;
+
+// Simulated time without a single game event after which the run is taken to be stuck
+const MAX_IDLE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeadlessError {
+    Stuck,
+    GameOver,
+    OutOfThrows { bowler: String },
+    BadThrow(String),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::Stuck => write!(f, "nothing happened for {} simulated seconds", MAX_IDLE.as_secs()),
+            HeadlessError::GameOver => write!(f, "the game is already over"),
+            HeadlessError::OutOfThrows { bowler } => write!(f, "no throws left for {bowler}"),
+            HeadlessError::BadThrow(line) => write!(
+                f,
+                "bad throw '{line}', expected <position> <speed> <launch angle> <rev rate> <axis rotation> [<axis tilt>]"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+// The game without a window or a GPU. Physics and the game flow are stepped as fast as they go on
+// a clock that only moves with the simulation, and the bots are seeded, so the same lineup, throws
+// and seed always knock down the same pins.
+pub struct Headless {
+    physics: Physics,
+    game: Game<ManualClock>,
+    // everything the game reported since it was last drained
+    events: Vec<GameEvent>,
+}
+
+impl Headless {
+    pub fn new(
+        mut lineup: Lineup,
+        variant: Variant,
+        pattern: OilPattern,
+        config: PhysicsConfig,
+        physics_rate: u32,
+        seed: u64,
+    ) -> Self {
+        let mut physics = Physics::new(LaneSpec::REGULATION);
+        physics.set_config(config);
+        physics.set_oil_pattern(pattern);
+        physics.set_variant(variant.clone());
+        physics.build_colliders();
+        physics.set_timestep(1.0 / physics_rate as f32);
+        lineup.seed_bots(seed);
+        Self { physics, game: Game::new(ManualClock::default(), lineup, variant), events: Vec::new() }
+    }
+
+    pub fn lineup(&self) -> &Lineup {
+        self.game.lineup()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.game.phase() == GamePhase::GameOver
+    }
+
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // One physics step and what the game makes of it
+    pub fn step(&mut self) {
+        self.physics.simulate();
+        self.game.clock().advance(Duration::from_secs_f32(self.physics.timestep()));
+        self.game.update(&mut self.physics);
+        self.events.extend(self.game.drain_events());
+    }
+
+    // Steps until the condition holds, as long as something keeps happening
+    fn step_until(&mut self, done: impl Fn(&Self) -> bool) -> Result<(), HeadlessError> {
+        let mut idle = Duration::ZERO;
+        while !done(self) {
+            let events = self.events.len();
+            self.step();
+            idle = if self.events.len() > events {
+                Duration::ZERO
+            } else {
+                idle + Duration::from_secs_f32(self.physics.timestep())
+            };
+            if idle > MAX_IDLE {
+                return Err(HeadlessError::Stuck);
+            }
+        }
+        Ok(())
+    }
+
+    // Steps until a bowler at the keyboard is on the approach with the rack set, or the game is over.
+    // Bots bowl their balls on the way
+    pub fn wait_for_throw(&mut self) -> Result<(), HeadlessError> {
+        self.step_until(|headless| headless.game.can_throw() || headless.is_over())
+    }
+
+    // Throws for the bowler up and steps until the ball is scored, gives back the pins left standing
+    pub fn throw(&mut self, throw: Throw) -> Result<PinSet, HeadlessError> {
+        self.wait_for_throw()?;
+        if self.is_over() {
+            return Err(HeadlessError::GameOver);
+        }
+        let thrown = self.events.len();
+        self.game.throw(&mut self.physics, throw);
+        self.step_until(|headless| headless.game.phase() == GamePhase::Scoring)?;
+        let standing = self.events[thrown..].iter().rev().find_map(|event| match event {
+            GameEvent::BallScored { standing, .. } | GameEvent::PracticeBall { standing, .. } => Some(*standing),
+            _ => None,
+        });
+        Ok(standing.unwrap_or_else(|| self.physics.standing_pins()))
+    }
+
+    // Plays the game to the end, the bowlers at the keyboard throw the given throws in turn
+    pub fn play(&mut self, throws: impl IntoIterator<Item = Throw>) -> Result<(), HeadlessError> {
+        let mut throws = throws.into_iter();
+        loop {
+            self.wait_for_throw()?;
+            if self.is_over() {
+                return Ok(());
+            }
+            let throw = throws.next().ok_or_else(|| HeadlessError::OutOfThrows { bowler: self.lineup().bowler_name() })?;
+            self.throw(throw)?;
+        }
+    }
+}
+
+// Throws one per line, "<position> <speed> <launch angle> <rev rate> <axis rotation> [<axis tilt>]"
+// in the units of Throw, lines starting with # are comments
+pub fn parse_throws(text: &str) -> Result<Vec<Throw>, HeadlessError> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| parse_throw(line).ok_or_else(|| HeadlessError::BadThrow(line.to_string())))
+        .collect()
+}
+
+fn parse_throw(line: &str) -> Option<Throw> {
+    let numbers = line.split_whitespace().map(|field| field.parse::<f32>().ok()).collect::<Option<Vec<f32>>>()?;
+    let (position, speed, launch_angle, rev_rate, axis_rotation, axis_tilt) = match numbers[..] {
+        [position, speed, launch_angle, rev_rate, axis_rotation] => {
+            (position, speed, launch_angle, rev_rate, axis_rotation, Throw::default().axis_tilt)
+        }
+        [position, speed, launch_angle, rev_rate, axis_rotation, axis_tilt] => {
+            (position, speed, launch_angle, rev_rate, axis_rotation, axis_tilt)
+        }
+        _ => return None,
+    };
+    Some(Throw { position, speed, launch_angle, rev_rate, axis_rotation, axis_tilt })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "
+        # pocket, high on the head pin, light and wide
+        0 8 0 300 0
+        0.05 7.5 1 350 30 10
+        -0.1 8.5 -1 250 -15
+        0.3 7 -2 300 45 5
+    ";
+
+    // Every ball of a game of the script against a seeded bot: the pins left standing and the totals
+    fn play(seed: u64) -> (Vec<PinSet>, Vec<u16>) {
+        let lineup = Lineup::from_args(["Human".to_string(), "Bot@180".to_string()].into_iter()).unwrap();
+        let pattern = OilPattern::parse(include_str!("../res/patterns/house.txt")).unwrap();
+        let mut run = Headless::new(lineup, Variant::default(), pattern, PhysicsConfig::default(), 120, seed);
+        let mut throws = parse_throws(SCRIPT).unwrap().into_iter().cycle();
+        let mut balls = Vec::new();
+        loop {
+            // the bot bowls its balls on the way
+            run.wait_for_throw().unwrap();
+            balls.extend(run.drain_events().into_iter().filter_map(|event| match event {
+                GameEvent::BallScored { standing, .. } => Some(standing),
+                _ => None,
+            }));
+            if run.is_over() {
+                break;
+            }
+            run.throw(throws.next().unwrap()).unwrap();
+        }
+        let totals = run.lineup().players().iter().map(|player| player.score.total()).collect();
+        (balls, totals)
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let (balls, totals) = play(7);
+        // at least a strike a frame and two balls in the tenth for each of the two
+        assert!(balls.len() >= 2 * 11, "only {} balls", balls.len());
+        assert_eq!(play(7), (balls, totals));
+    }
+}
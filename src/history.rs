@@ -0,0 +1,472 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::phys::PinSet;
+use super::players::{Lineup, Player};
+use super::scoring::{ScoreError, ScoreSheet};
+use super::variant::{Variant, VariantKind};
+
+// Finished games, one plain text file each under the user's data directory:
+//
+//   version 1
+//   played 1792310400
+//   variant ten
+//   oil house
+//   player Ania
+//   handicap 12
+//   ball 1 9 10
+//   ball 1 1 -
+//   ...
+//
+// A player line starts the player, the lines up to the next one are theirs. Every ball gives
+// its frame, the pins it knocked down and the pins it left standing, "split" marks a split.
+// Bots give their average with "bot", Baker teams list their bowlers with "member".
+pub const VERSION: u32 = 1;
+const EXTENSION: &str = "txt";
+// our directory under the user's data directory
+pub const DATA_DIR: &str = "rust_project";
+
+#[derive(Debug)]
+pub enum HistoryError {
+    Io(io::Error),
+    MissingVersion,
+    UnsupportedVersion(u32),
+    BadValue { key: String, value: String },
+    UnknownKey(String),
+    BadBall { player: String, error: ScoreError },
+    // an id that would reach outside the games directory
+    BadId(String),
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::Io(e) => write!(f, "{e}"),
+            HistoryError::MissingVersion => write!(f, "not a saved game, the version line is missing"),
+            HistoryError::UnsupportedVersion(version) => {
+                write!(f, "saved game version {version} is not supported, {VERSION} is the latest")
+            }
+            HistoryError::BadValue { key, value } => write!(f, "bad value '{value}' for '{key}' in saved game"),
+            HistoryError::UnknownKey(key) => write!(f, "unknown key '{key}' in saved game"),
+            HistoryError::BadBall { player, error } => write!(f, "bad ball for {player} in saved game: {error}"),
+            HistoryError::BadId(id) => write!(f, "'{id}' is not the id of a saved game"),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+impl From<io::Error> for HistoryError {
+    fn from(e: io::Error) -> Self {
+        HistoryError::Io(e)
+    }
+}
+
+// One player's (or Baker team's) game as it was bowled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerRecord {
+    pub name: String,
+    // average of a computer bowler
+    pub bot: Option<u16>,
+    pub handicap: Option<u16>,
+    pub members: Vec<String>,
+    pub score: ScoreSheet,
+}
+
+impl PlayerRecord {
+    pub fn total_with_handicap(&self) -> u16 {
+        self.score.total() + self.handicap.unwrap_or(0)
+    }
+}
+
+impl From<&Player> for PlayerRecord {
+    fn from(player: &Player) -> Self {
+        Self {
+            name: player.name.clone(),
+            bot: player.bot.as_ref().map(|bot| bot.skill.average),
+            handicap: player.handicap,
+            members: player.members.iter().map(|member| member.name.clone()).collect(),
+            score: player.score.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    // seconds since the Unix epoch
+    pub played: u64,
+    pub variant: VariantKind,
+    pub oil: String,
+    pub players: Vec<PlayerRecord>,
+}
+
+impl GameRecord {
+    // The game the lineup has just finished
+    pub fn new(lineup: &Lineup, variant: VariantKind, oil: &str) -> Self {
+        let played = SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0);
+        Self { played, variant, oil: oil.to_string(), players: lineup.players().iter().map(PlayerRecord::from).collect() }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, HistoryError> {
+        let lines = lines(text)?;
+        let mut record = Self { played: 0, variant: VariantKind::TenPin, oil: String::new(), players: Vec::new() };
+        for line in lines {
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            let bad_value = || HistoryError::BadValue { key: key.to_string(), value: value.to_string() };
+            if key == "player" {
+                let rules = Variant::new(record.variant).rules;
+                record.players.push(PlayerRecord {
+                    name: value.to_string(),
+                    bot: None,
+                    handicap: None,
+                    members: Vec::new(),
+                    score: ScoreSheet::with_rules(rules),
+                });
+                continue;
+            }
+            match (key, record.players.last_mut()) {
+                ("played", None) => record.played = value.parse().map_err(|_| bad_value())?,
+                ("variant", None) => record.variant = VariantKind::parse(value).ok_or_else(bad_value)?,
+                ("oil", None) => record.oil = value.to_string(),
+                ("bot", Some(player)) => player.bot = Some(value.parse().map_err(|_| bad_value())?),
+                ("handicap", Some(player)) => player.handicap = Some(value.parse().map_err(|_| bad_value())?),
+                ("member", Some(player)) => player.members.push(value.to_string()),
+                ("ball", Some(player)) => read_ball(&mut player.score, &player.name, value)?,
+                _ => return Err(HistoryError::UnknownKey(key.to_string())),
+            }
+        }
+        Ok(record)
+    }
+
+    // The game in the format parse reads
+    pub fn to_text(&self) -> String {
+        let mut text = format!("version {VERSION}\nplayed {}\nvariant {}\noil {}\n", self.played, self.variant.id(), self.oil);
+        for player in &self.players {
+            text.push_str(&format!("player {}\n", player.name));
+            if let Some(average) = player.bot {
+                text.push_str(&format!("bot {average}\n"));
+            }
+            if let Some(handicap) = player.handicap {
+                text.push_str(&format!("handicap {handicap}\n"));
+            }
+            for member in &player.members {
+                text.push_str(&format!("member {member}\n"));
+            }
+            text.push_str(&balls_to_text(&player.score));
+        }
+        text
+    }
+
+    // One line for the list of games
+    pub fn summary(&self) -> String {
+        let players: Vec<String> =
+            self.players.iter().map(|player| format!("{} {}", player.name, player.total_with_handicap())).collect();
+        format!("{}  {}, {}: {}", date(self.played), Variant::new(self.variant).name, self.oil, players.join(", "))
+    }
+}
+
+// The lines of a file after its version line, which has to be the first one. Everything after it
+// is read the way that version wrote it
+pub fn lines(text: &str) -> Result<impl Iterator<Item = &str>, HistoryError> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+    match lines.next().and_then(|line| line.strip_prefix("version ")) {
+        Some(version) => match version.trim().parse() {
+            Ok(VERSION) => Ok(lines),
+            Ok(version) => Err(HistoryError::UnsupportedVersion(version)),
+            Err(_) => Err(HistoryError::BadValue { key: "version".to_string(), value: version.to_string() }),
+        },
+        None => Err(HistoryError::MissingVersion),
+    }
+}
+
+// A ball line for every ball on the score sheet
+pub fn balls_to_text(score: &ScoreSheet) -> String {
+    let mut text = String::new();
+    for (index, frame) in score.frames().iter().enumerate() {
+        for (ball, &knocked) in frame.rolls.iter().enumerate() {
+            let standing = frame.leaves.get(ball).copied().unwrap_or_default();
+            let standing = if standing.len() == 0 { "-".to_string() } else { standing.to_string() };
+            let split = if frame.splits.contains(&ball) { " split" } else { "" };
+            text.push_str(&format!("ball {} {knocked} {standing}{split}\n", index + 1));
+        }
+    }
+    text
+}
+
+// Puts the ball of a ball line on the player's score sheet, balls have to come in order
+pub fn read_ball(score: &mut ScoreSheet, player: &str, value: &str) -> Result<(), HistoryError> {
+    let bad_value = || HistoryError::BadValue { key: "ball".to_string(), value: value.to_string() };
+    let (frame, knocked, standing, split) = parse_ball(value).ok_or_else(bad_value)?;
+    if frame != score.current_frame() {
+        return Err(bad_value());
+    }
+    score.roll(knocked).map_err(|error| HistoryError::BadBall { player: player.to_string(), error })?;
+    score.mark_leave(standing);
+    if split {
+        score.mark_split();
+    }
+    Ok(())
+}
+
+// "1 9 10", "3 8 7-10 split": the frame counts from 1, "-" is a clean deck
+fn parse_ball(value: &str) -> Option<(usize, u8, PinSet, bool)> {
+    let mut fields = value.split_whitespace();
+    let frame = fields.next()?.parse::<usize>().ok()?.checked_sub(1)?;
+    let knocked = fields.next()?.parse().ok()?;
+    let mut standing = PinSet::default();
+    match fields.next()? {
+        "-" => {}
+        pins => {
+            for pin in pins.split('-') {
+                standing.insert(pin.parse().ok().filter(|pin| (1..16).contains(pin))?);
+            }
+        }
+    }
+    let split = match fields.next() {
+        Some("split") => true,
+        Some(_) => return None,
+        None => false,
+    };
+    fields.next().is_none().then_some((frame, knocked, standing, split))
+}
+
+// "2026-10-18 14:05" in UTC, days to the civil date as in Howard Hinnant's algorithm
+fn date(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    let minutes = seconds % 86400 / 60;
+    format!("{year}-{month:02}-{day:02} {:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}  {}, {}", date(self.played), Variant::new(self.variant).name, self.oil)?;
+        for player in &self.players {
+            write!(f, "{}", player.name)?;
+            if let Some(average) = player.bot {
+                write!(f, " (bot averaging {average})")?;
+            }
+            if !player.members.is_empty() {
+                write!(f, " ({})", player.members.join(", "))?;
+            }
+            match player.handicap {
+                Some(handicap) => writeln!(
+                    f,
+                    ", scratch {}, handicap {handicap}, total {}",
+                    player.score.total(),
+                    player.total_with_handicap()
+                )?,
+                None => writeln!(f, ", total {}", player.score.total())?,
+            }
+            writeln!(f, "{}", player.score)?;
+        }
+        Ok(())
+    }
+}
+
+// Where the games are kept, a game's id is the name of its file
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    // rust_project/games under the platform's data directory, None without a home to put it in
+    pub fn open() -> Option<Self> {
+        data_dir().map(|dir| Self::new(dir.join(DATA_DIR).join("games")))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Ids come from the command line, only plain file names are taken: no separators or drives, no dots for ".."
+    // or an extension
+    fn path(&self, id: &str) -> Result<PathBuf, HistoryError> {
+        if id.is_empty() || id.contains(['/', '\\', ':', '.']) || Path::new(id).is_absolute() {
+            return Err(HistoryError::BadId(id.to_string()));
+        }
+        Ok(self.dir.join(id).with_extension(EXTENSION))
+    }
+
+    // Ids of the saved games, oldest first
+    pub fn list(&self) -> Result<Vec<String>, HistoryError> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            //nothing has been saved yet
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut ids = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == EXTENSION) {
+                if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    ids.push(id.to_string());
+                }
+            }
+        }
+        //ids are the time the game was played, with a count after it when two finished together
+        ids.sort_by_cached_key(|id| {
+            let (played, count) = id.split_once('-').unwrap_or((id, "1"));
+            (played.parse::<u64>().unwrap_or(0), count.parse::<u32>().unwrap_or(1), id.clone())
+        });
+        Ok(ids)
+    }
+
+    pub fn load(&self, id: &str) -> Result<GameRecord, HistoryError> {
+        GameRecord::parse(&std::fs::read_to_string(self.path(id)?)?)
+    }
+
+    // Writes the game to a file of its own and gives back its id
+    pub fn save(&self, record: &GameRecord) -> Result<String, HistoryError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let mut id = record.played.to_string();
+        let mut count = 1;
+        while self.path(&id)?.exists() {
+            count += 1;
+            id = format!("{}-{count}", record.played);
+        }
+        std::fs::write(self.path(&id)?, record.to_text())?;
+        Ok(id)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), HistoryError> {
+        Ok(std::fs::remove_file(self.path(id)?)?)
+    }
+}
+
+// XDG_DATA_HOME or ~/.local/share, the roaming AppData on Windows and Application Support on macOS
+pub fn data_dir() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        return var("APPDATA");
+    }
+    if cfg!(target_os = "macos") {
+        return var("HOME").map(|home| home.join("Library").join("Application Support"));
+    }
+    var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local").join("share")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pins(numbers: &[u8]) -> PinSet {
+        let mut pins = PinSet::default();
+        for &pin in numbers {
+            pins.insert(pin);
+        }
+        pins
+    }
+
+    // A game of strikes, 7-10 splits left open and spares, with what every ball left standing
+    fn bowl(score: &mut ScoreSheet) {
+        let mut frame = 0;
+        while !score.is_complete() {
+            let balls: &[(u8, &[u8], bool)] = match frame % 3 {
+                0 => &[(10, &[], false)],
+                1 => &[(8, &[7, 10], true), (1, &[7], false)],
+                _ => &[(9, &[4], false), (1, &[], false)],
+            };
+            for &(knocked, standing, split) in balls {
+                if score.is_complete() {
+                    break;
+                }
+                score.roll(knocked).unwrap();
+                score.mark_leave(pins(standing));
+                if split {
+                    score.mark_split();
+                }
+            }
+            frame += 1;
+        }
+    }
+
+    fn temp_history(name: &str) -> (PathBuf, History) {
+        let root = std::env::temp_dir().join(format!("{DATA_DIR}-{name}-{}", std::process::id()));
+        let history = History::new(root.join("games"));
+        (root, history)
+    }
+
+    #[test]
+    fn finished_games_come_back_as_they_were_saved() {
+        let args = ["Ania", "Robot@180", "Team=Ola,Ewa"].map(String::from);
+        let mut lineup = Lineup::from_args(args.into_iter()).unwrap();
+        for player in lineup.players_mut() {
+            bowl(&mut player.score);
+        }
+        lineup.players_mut()[0].handicap = Some(12);
+        let mut record = GameRecord::new(&lineup, VariantKind::TenPin, "house");
+        record.played = 1_792_310_400;
+        assert_eq!(record.players[1].bot, Some(180));
+        assert_eq!(record.players[2].members, ["Ola", "Ewa"]);
+
+        let (root, history) = temp_history("round-trip");
+        assert_eq!(history.list().unwrap(), Vec::<String>::new());
+        let first = history.save(&record).unwrap();
+        // a second game finished in the same second gets an id of its own
+        let second = history.save(&record).unwrap();
+        assert_eq!([first.as_str(), second.as_str()], ["1792310400", "1792310400-2"]);
+        assert_eq!(history.list().unwrap(), [first.clone(), second.clone()]);
+        let loaded = history.load(&first).unwrap();
+        assert_eq!(loaded, record);
+        assert_eq!(loaded.players[0].score.frames(), lineup.players()[0].score.frames());
+        assert_eq!(loaded.players[0].total_with_handicap(), lineup.players()[0].total_with_handicap());
+        history.delete(&first).unwrap();
+        assert_eq!(history.list().unwrap(), std::slice::from_ref(&second));
+        assert!(matches!(history.load(&first), Err(HistoryError::Io(_))));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let record =
+            GameRecord { played: 0, variant: VariantKind::TenPin, oil: "house".to_string(), players: Vec::new() };
+        let newer = record.to_text().replacen(&format!("version {VERSION}"), &format!("version {}", VERSION + 1), 1);
+        let refused = GameRecord::parse(&newer);
+        assert!(matches!(refused, Err(HistoryError::UnsupportedVersion(version)) if version == VERSION + 1), "{refused:?}");
+        assert!(matches!(GameRecord::parse("version x\n"), Err(HistoryError::BadValue { .. })));
+        assert!(matches!(GameRecord::parse("played 0\n"), Err(HistoryError::MissingVersion)));
+        assert_eq!(GameRecord::parse(&record.to_text()).unwrap(), record);
+
+        let (root, history) = temp_history("version");
+        std::fs::create_dir_all(history.dir()).unwrap();
+        std::fs::write(history.dir().join("1700000000.txt"), newer).unwrap();
+        assert!(matches!(history.load("1700000000"), Err(HistoryError::UnsupportedVersion(_))));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn ids_stay_in_the_games_directory() {
+        let root = std::env::temp_dir().join(format!("{DATA_DIR}-history-{}", std::process::id()));
+        let history = History::new(root.join("games"));
+        std::fs::create_dir_all(history.dir()).unwrap();
+        let outside = root.join("saved_game.txt");
+        std::fs::write(&outside, "keep").unwrap();
+        let absolute = outside.with_extension("").to_string_lossy().into_owned();
+        for id in ["../saved_game", "..", ".", "", "a/b", "a\\b", "x.txt", absolute.as_str()] {
+            assert!(matches!(history.delete(id), Err(HistoryError::BadId(_))), "{id}");
+            assert!(matches!(history.load(id), Err(HistoryError::BadId(_))), "{id}");
+        }
+        assert!(outside.exists());
+        std::fs::write(history.dir().join("1700000000.txt"), "").unwrap();
+        assert_eq!(history.list().unwrap(), ["1700000000"]);
+        history.delete("1700000000").unwrap();
+        assert_eq!(history.list().unwrap(), Vec::<String>::new());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+// Lane geometry, in metres. The lane runs along +z starting at the foul line (z = 0),
+// the bowler's left is +x and the lane surface is at y = 0.
+const INCH: f32 = 0.0254;
+const FOOT: f32 = 12.0 * INCH;
+// boards across the lane bed, board 1 is at the bowler's right
+pub const BOARDS: usize = 39;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LaneSpec {
+    pub approach_length: f32,
+    pub foul_line_to_head_pin: f32,
+    pub head_pin_to_pit: f32,
+    pub lane_width: f32,
+    pub gutter_width: f32,
+    pub gutter_depth: f32,
+    pub pit_depth: f32,
+    // from the end of the pin deck to the cushion
+    pub pit_length: f32,
+    // kickbacks run from this far in front of the pit to the cushion
+    pub kickback_length: f32,
+    pub kickback_height: f32,
+    pub wall_thickness: f32,
+    // depth of the slabs under every surface, thick enough that nothing tunnels through
+    pub slab_depth: f32,
+}
+
+impl LaneSpec {
+    // USBC ten-pin lane
+    pub const REGULATION: LaneSpec = LaneSpec {
+        approach_length: 15.0 * FOOT,
+        foul_line_to_head_pin: 60.0 * FOOT,
+        head_pin_to_pit: 2.0 * FOOT + 10.1875 * INCH,
+        lane_width: 41.5 * INCH,
+        gutter_width: 9.25 * INCH,
+        gutter_depth: 1.875 * INCH,
+        pit_depth: 10.0 * INCH,
+        pit_length: 40.0 * INCH,
+        kickback_length: 6.0 * FOOT,
+        kickback_height: 20.0 * INCH,
+        wall_thickness: 2.0 * INCH,
+        slab_depth: 6.0 * INCH,
+    };
+
+    // foul line to the edge of the pit
+    pub fn lane_length(&self) -> f32 {
+        self.foul_line_to_head_pin + self.head_pin_to_pit
+    }
+
+    // lane bed plus both gutters
+    pub fn half_width(&self) -> f32 {
+        self.lane_width / 2.0 + self.gutter_width
+    }
+
+    pub fn board_width(&self) -> f32 {
+        self.lane_width / BOARDS as f32
+    }
+
+    // x of the middle of a board, the bowler's right edge is -x
+    pub fn board_x(&self, board: u8) -> f32 {
+        -self.lane_width / 2.0 + (board as f32 - 0.5) * self.board_width()
+    }
+
+    // Solid pieces of the lane, shared by the colliders and the lane mesh
+    pub fn blocks(&self) -> Vec<Block> {
+        let t = self.wall_thickness;
+        let d = self.slab_depth;
+        let lane = self.lane_width / 2.0;
+        let outer = self.half_width();
+        let end = self.lane_length();
+        let cushion = end + self.pit_length;
+        let kickbacks = end - self.kickback_length;
+        let mut blocks = vec![
+            Block::new(Surface::Approach, [-outer - t, -d, -self.approach_length], [outer + t, 0.0, 0.0]),
+            Block::new(Surface::Lane, [-lane, -d, 0.0], [lane, 0.0, end]),
+            Block::new(Surface::Pit, [-outer, -self.pit_depth - d, end], [outer, -self.pit_depth, cushion]),
+            Block::new(
+                Surface::Cushion,
+                [-outer - t, -self.pit_depth - t, cushion],
+                [outer + t, self.kickback_height, cushion + d],
+            ),
+        ];
+        for side in [-1.0f32, 1.0] {
+            let x_range = |inner: f32, outer: f32| {
+                let (a, b) = (side * inner, side * outer);
+                (a.min(b), a.max(b))
+            };
+            let (x0, x1) = x_range(lane, outer);
+            blocks.push(Block::new(
+                Surface::Gutter,
+                [x0, -self.gutter_depth - d, 0.0],
+                [x1, -self.gutter_depth, end],
+            ));
+            // capping along the gutter, flush with the lane, up to where the kickbacks start
+            let (x0, x1) = x_range(outer, outer + t);
+            blocks.push(Block::new(Surface::Kickback, [x0, -self.gutter_depth - t, 0.0], [x1, 0.0, kickbacks]));
+            blocks.push(Block::new(
+                Surface::Kickback,
+                [x0, -self.pit_depth - t, kickbacks],
+                [x1, self.kickback_height, cushion],
+            ));
+        }
+        blocks
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Surface {
+    Approach,
+    Lane,
+    Gutter,
+    Pit,
+    Kickback,
+    Cushion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Block {
+    pub surface: Surface,
+    pub center: [f32; 3],
+    pub half_extents: [f32; 3],
+}
+
+impl Block {
+    fn new(surface: Surface, min: [f32; 3], max: [f32; 3]) -> Self {
+        let center = [0, 1, 2].map(|i| (min[i] + max[i]) / 2.0);
+        let half_extents = [0, 1, 2].map(|i| (max[i] - min[i]) / 2.0);
+        Self { surface, center, half_extents }
+    }
+}
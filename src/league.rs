@@ -0,0 +1,253 @@
+use std::fmt;
+
+// League books: every bowler's series, one per session, and the handicap rules. The file is
+// kept as plain text so it can still be fixed up by hand:
+//
+//   base 220
+//   percentage 90
+//   series Ania: 180 192 201
+//   series Bartek: 150 162 171
+//
+// A series line is added for every bowler each session, the average is taken over all of them.
+const DEFAULT_BASE: u16 = 220;
+const DEFAULT_PERCENTAGE: u16 = 90;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LeagueError {
+    BadValue { key: String, value: String },
+    UnknownKey(String),
+}
+
+impl fmt::Display for LeagueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeagueError::BadValue { key, value } => write!(f, "bad value '{value}' for '{key}' in league file"),
+            LeagueError::UnknownKey(key) => write!(f, "unknown key '{key}' in league file"),
+        }
+    }
+}
+
+impl std::error::Error for LeagueError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bowler {
+    pub name: String,
+    pub series: Vec<Vec<u16>>,
+}
+
+impl Bowler {
+    pub fn games(&self) -> usize {
+        self.series.iter().map(Vec::len).sum()
+    }
+
+    pub fn pins(&self) -> u32 {
+        self.series.iter().flatten().map(|&score| score as u32).sum()
+    }
+
+    // USBC drops the fraction, None until the first game is in the books
+    pub fn average(&self) -> Option<u16> {
+        let games = self.games();
+        (games > 0).then(|| (self.pins() / games as u32) as u16)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct League {
+    // the average handicap is counted from, usually above the best bowler's
+    pub base: u16,
+    // share of the difference to the base given as handicap
+    pub percentage: u16,
+    pub bowlers: Vec<Bowler>,
+    // bowlers who have bowled in this session, they add games to their last series
+    session: Vec<String>,
+}
+
+impl League {
+    pub fn new() -> Self {
+        Self { base: DEFAULT_BASE, percentage: DEFAULT_PERCENTAGE, bowlers: Vec::new(), session: Vec::new() }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, LeagueError> {
+        let mut league = Self::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            let bad_value = || LeagueError::BadValue { key: key.to_string(), value: value.to_string() };
+            match key {
+                "base" => league.base = value.parse().map_err(|_| bad_value())?,
+                "percentage" => {
+                    league.percentage = value.parse().ok().filter(|percentage| *percentage <= 100).ok_or_else(bad_value)?
+                }
+                "series" => {
+                    let (name, scores) = value.split_once(':').ok_or_else(bad_value)?;
+                    let games = scores
+                        .split_whitespace()
+                        .map(|score| score.parse::<u16>().ok().filter(|score| *score <= 300))
+                        .collect::<Option<Vec<u16>>>()
+                        .ok_or_else(bad_value)?;
+                    league.bowler_mut(name.trim()).series.push(games);
+                }
+                _ => return Err(LeagueError::UnknownKey(key.to_string())),
+            }
+        }
+        Ok(league)
+    }
+
+    // The books in the format parse reads
+    pub fn to_text(&self) -> String {
+        let mut text = format!("base {}\npercentage {}\n", self.base, self.percentage);
+        for bowler in &self.bowlers {
+            for series in &bowler.series {
+                let games: Vec<String> = series.iter().map(|score| score.to_string()).collect();
+                text.push_str(&format!("series {}: {}\n", bowler.name, games.join(" ")));
+            }
+        }
+        text
+    }
+
+    pub fn bowler(&self, name: &str) -> Option<&Bowler> {
+        self.bowlers.iter().find(|bowler| bowler.name == name)
+    }
+
+    fn bowler_mut(&mut self, name: &str) -> &mut Bowler {
+        match self.bowlers.iter().position(|bowler| bowler.name == name) {
+            Some(index) => &mut self.bowlers[index],
+            None => {
+                self.bowlers.push(Bowler { name: name.to_string(), series: Vec::new() });
+                self.bowlers.last_mut().unwrap()
+            }
+        }
+    }
+
+    // Handicap from the average the bowler brings in, bowlers without one get none
+    pub fn handicap(&self, name: &str) -> u16 {
+        let average = self.bowler(name).and_then(Bowler::average).unwrap_or(self.base);
+        (self.base.saturating_sub(average) as u32 * self.percentage as u32 / 100) as u16
+    }
+
+    // Puts a finished game into the bowler's series for this session
+    pub fn record(&mut self, name: &str, score: u16) {
+        let first_game = !self.session.iter().any(|bowled| bowled == name);
+        if first_game {
+            self.session.push(name.to_string());
+        }
+        let bowler = self.bowler_mut(name);
+        match bowler.series.last_mut() {
+            Some(series) if !first_game => series.push(score),
+            _ => bowler.series.push(vec![score]),
+        }
+    }
+}
+
+impl Default for League {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Standings, best average first
+impl fmt::Display for League {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bowlers: Vec<&Bowler> = self.bowlers.iter().collect();
+        bowlers.sort_by_key(|bowler| std::cmp::Reverse(bowler.average()));
+        writeln!(f, "{:<16}{:>6}{:>7}{:>5}{:>6}  Last series", "Bowler", "Games", "Pins", "Avg", "Hdcp")?;
+        for bowler in bowlers {
+            let last = bowler.series.last().map(|series| {
+                let games: Vec<String> = series.iter().map(|score| score.to_string()).collect();
+                format!("{} ({})", games.join("-"), series.iter().sum::<u16>())
+            });
+            writeln!(
+                f,
+                "{:<16}{:>6}{:>7}{:>5}{:>6}  {}",
+                bowler.name,
+                bowler.games(),
+                bowler.pins(),
+                bowler.average().unwrap_or(0),
+                self.handicap(&bowler.name),
+                last.unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOKS: &str =
+        "base 220\npercentage 90\nseries Ania: 180 192 201\nseries Bartek: 150 162 171\nseries Ania: 199\n";
+
+    #[test]
+    fn average_drops_the_fraction() {
+        let league = League::parse(BOOKS).unwrap();
+        let ania = league.bowler("Ania").unwrap();
+        assert_eq!((ania.games(), ania.pins()), (4, 772));
+        assert_eq!(ania.average(), Some(193));
+        // 483 over three is exactly 161
+        assert_eq!(league.bowler("Bartek").unwrap().average(), Some(161));
+        let bowler = Bowler { name: "Celina".to_string(), series: vec![vec![100, 101]] };
+        assert_eq!(bowler.average(), Some(100));
+        assert_eq!(Bowler { name: "Daria".to_string(), series: Vec::new() }.average(), None);
+    }
+
+    #[test]
+    fn handicap_is_a_share_of_the_way_to_the_base() {
+        let mut league = League::parse(BOOKS).unwrap();
+        // (220 - 193) * 90 / 100 = 24.3
+        assert_eq!(league.handicap("Ania"), 24);
+        // (220 - 161) * 90 / 100 = 53.1
+        assert_eq!(league.handicap("Bartek"), 53);
+        // no average yet, no handicap
+        assert_eq!(league.handicap("Celina"), 0);
+        // an average above the base doesn't take pins away
+        league.record("Celina", 240);
+        assert_eq!(league.handicap("Celina"), 0);
+        league.percentage = 100;
+        assert_eq!(league.handicap("Bartek"), 59);
+        league.percentage = 0;
+        assert_eq!(league.handicap("Bartek"), 0);
+    }
+
+    #[test]
+    fn a_session_adds_one_series() {
+        let mut league = League::parse(BOOKS).unwrap();
+        for score in [170, 180, 190] {
+            league.record("Bartek", score);
+            league.record("Ewa", score + 10);
+        }
+        assert_eq!(league.bowler("Bartek").unwrap().series, [vec![150, 162, 171], vec![170, 180, 190]]);
+        assert_eq!(league.bowler("Ewa").unwrap().series, [vec![180, 190, 200]]);
+        // the next session starts from the books
+        let mut next = League::parse(&league.to_text()).unwrap();
+        next.record("Ewa", 210);
+        assert_eq!(next.bowler("Ewa").unwrap().series, [vec![180, 190, 200], vec![210]]);
+    }
+
+    #[test]
+    fn books_come_back_from_their_text() {
+        let mut league = League::parse(BOOKS).unwrap();
+        league.base = 230;
+        league.percentage = 80;
+        league.record("Celina", 155);
+        let text = league.to_text();
+        assert!(text.starts_with("base 230\npercentage 80\nseries Ania: 180 192 201\nseries Ania: 199\n"), "{text}");
+        let mut read = League::parse(&text).unwrap();
+        // the session is not part of the books
+        read.session = league.session.clone();
+        assert_eq!(read, league);
+        assert_eq!(League::parse("").unwrap(), League::new());
+        assert_eq!(League::parse("# no games yet\n\nbase 200").unwrap().base, 200);
+    }
+
+    #[test]
+    fn bad_books_are_refused() {
+        let bad_value =
+            |key: &str, value: &str| LeagueError::BadValue { key: key.to_string(), value: value.to_string() };
+        assert_eq!(League::parse("percentage 101"), Err(bad_value("percentage", "101")));
+        assert_eq!(League::parse("base x"), Err(bad_value("base", "x")));
+        assert_eq!(League::parse("series Ania 180"), Err(bad_value("series", "Ania 180")));
+        assert_eq!(League::parse("series Ania: 180 301"), Err(bad_value("series", "Ania: 180 301")));
+        assert_eq!(League::parse("handicap 20"), Err(LeagueError::UnknownKey("handicap".to_string())));
+    }
+}
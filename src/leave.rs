@@ -0,0 +1,153 @@
+use std::fmt;
+
+use super::phys::PinSet;
+use super::variant::ten_pin_layout;
+
+// Leaves with their own name, the pins are listed as they stand after the first ball
+const NAMED_LEAVES: &[(&[u8], &str)] = &[
+    (&[7, 10], "7-10 split"),
+    (&[4, 6, 7, 10], "Big Four"),
+    (&[4, 6, 7, 8, 10], "Greek Church"),
+    (&[4, 6, 7, 9, 10], "Greek Church"),
+    (&[2, 4, 5, 8], "Bucket"),
+    (&[3, 5, 6, 9], "Bucket"),
+    (&[2, 7], "Baby split"),
+    (&[3, 10], "Baby split"),
+    (&[5, 7, 10], "Sour apple"),
+    (&[2, 7, 10], "Christmas tree"),
+    (&[3, 7, 10], "Christmas tree"),
+    (&[1, 2, 4, 7], "Picket fence"),
+    (&[1, 3, 6, 10], "Picket fence"),
+    (&[1, 2, 10], "Washout"),
+    (&[1, 2, 4, 10], "Washout"),
+    (&[1, 3, 7], "Washout"),
+    (&[1, 3, 6, 7], "Washout"),
+    (&[1, 5], "Sleeper"),
+    (&[2, 8], "Sleeper"),
+    (&[3, 9], "Sleeper"),
+];
+
+// Where a pin stands in the rack: its row and how far left of the centre line it is,
+// in half pin spacings
+#[derive(Debug, Clone, Copy)]
+struct Spot {
+    row: i32,
+    lateral: i32,
+}
+
+fn spot(pin: u8) -> Spot {
+    ten_pin_layout()
+        .find(|(number, _, _)| *number == pin)
+        .map(|(_, row, col)| Spot { row: row as i32, lateral: row as i32 - 2 * col as i32 })
+        .unwrap_or(Spot { row: 0, lateral: 0 })
+}
+
+// The pins left after the first ball at a full rack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Leave {
+    pub pins: PinSet,
+    pub name: Option<&'static str>,
+    // head pin down and a gap between the standing pins, circled on the scorecard
+    pub split: bool,
+    // the head pin up with a gap to a pin on the far side, the leaves named so
+    pub washout: bool,
+}
+
+impl Leave {
+    pub fn classify(standing: PinSet) -> Self {
+        let split = !standing.contains(1) && has_gap(standing);
+        let name = NAMED_LEAVES
+            .iter()
+            .find(|(pins, _)| pins.len() == standing.len() as usize && pins.iter().all(|&pin| standing.contains(pin)))
+            .map(|(_, name)| *name);
+        Self { pins: standing, name, split, washout: name == Some("Washout") }
+    }
+}
+
+// USBC split rules: some pin is down between two standing pins, or right in front of two standing pins
+fn has_gap(standing: PinSet) -> bool {
+    if standing.len() < 2 {
+        return false;
+    }
+    let up: Vec<Spot> = standing.iter().map(spot).collect();
+    ten_pin_layout().filter(|(pin, _, _)| !standing.contains(*pin)).any(|(pin, _, _)| {
+        let down = spot(pin);
+        let between = up.iter().any(|a| up.iter().any(|b| between(down, *a, *b, &up)));
+        let behind = |offset: i32| {
+            up.iter().any(|pin| pin.row == down.row + 1 && pin.lateral == down.lateral + offset)
+        };
+        between || (behind(1) && behind(-1))
+    })
+}
+
+// Sideways between a and b and not behind both of them. Only pins next to each other from side to
+// side count, a standing pin in between already bridges the gap, as the 2 and the 4 do for the 1-7
+fn between(pin: Spot, a: Spot, b: Spot, up: &[Spot]) -> bool {
+    let inside = |lateral: i32| a.lateral.min(b.lateral) < lateral && lateral < a.lateral.max(b.lateral);
+    inside(pin.lateral) && pin.row <= a.row.max(b.row) && !up.iter().any(|pin| inside(pin.lateral))
+}
+
+impl fmt::Display for Leave {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "{name} ({})", self.pins)?,
+            None => write!(f, "{}", self.pins)?,
+        }
+        if self.split && !self.name.is_some_and(|name| name.contains("split")) {
+            write!(f, ", split")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leave(pins: &[u8]) -> Leave {
+        let mut standing = PinSet::default();
+        for &pin in pins {
+            standing.insert(pin);
+        }
+        Leave::classify(standing)
+    }
+
+    #[test]
+    fn named_leaves() {
+        // pins, name, split, washout
+        let table: &[(&[u8], Option<&str>, bool, bool)] = &[
+            (&[7, 10], Some("7-10 split"), true, false),
+            (&[4, 6], None, true, false),
+            (&[6, 7, 10], None, true, false),
+            (&[2, 7], Some("Baby split"), true, false),
+            (&[3, 10], Some("Baby split"), true, false),
+            // the 2 pin is down right in front of the 4 and the 5
+            (&[4, 5, 7], None, true, false),
+            (&[4, 10], None, true, false),
+            (&[2, 10], None, true, false),
+            (&[4, 9], None, true, false),
+            (&[3, 7], None, true, false),
+            (&[6, 7], None, true, false),
+            (&[5, 7], None, true, false),
+            (&[5, 10], None, true, false),
+            (&[2, 4, 5, 7], None, false, false),
+            (&[1, 2, 10], Some("Washout"), false, true),
+            (&[1, 2, 4, 10], Some("Washout"), false, true),
+            (&[1, 3, 7], Some("Washout"), false, true),
+            (&[1, 2, 4, 7], Some("Picket fence"), false, false),
+            // a gap, but the head pin keeps it from being a split and it is no washout
+            (&[1, 2, 6], None, false, false),
+        ];
+        for &(pins, name, split, washout) in table {
+            let leave = leave(pins);
+            assert_eq!((leave.name, leave.split, leave.washout), (name, split, washout), "{pins:?}");
+        }
+    }
+
+    #[test]
+    fn pins_next_to_each_other_are_no_split() {
+        for pins in [&[1][..], &[10], &[2, 4], &[3, 6, 10], &[2, 4, 5, 8], &[5, 8], &[2, 8], &[3, 9]] {
+            assert!(!leave(pins).split, "{pins:?}");
+        }
+    }
+}
@@ -0,0 +1,31 @@
+// Debug lines, drawn in world space on top of everything
+struct Globals {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    ambient: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = globals.view_proj * vec4<f32>(model.position, 1.0);
+    out.color = model.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -351,6 +351,6 @@ impl Object{
         let m = (trans_mat * rotate_mat_z * rotate_mat_y * rotate_mat_x * scale_mat);
         //unfortunately have do to this conversion to send pod to gpu
         let model_mat: [f32;16] = *m.as_ref();
-        Self{model:(model),instances:(instances),id:(name), locals:(Locals { model_mat,color: ([0.0, 0.0, 1.0, 1.0]),normal: ([0.0, 0.0, 0.0, 0.0]),lights: ([0.0, 0.0, 0.0, 0.0]),})}
+        Self{model:(model),instances:(instances),id:(name), locals:(Locals { model_mat,color: ([1.0, 1.0, 1.0, 1.0]),normal: ([0.0, 0.0, 0.0, 0.0]),lights: ([0.0, 0.0, 0.0, 0.0]),})}
     }
 }
\ No newline at end of file
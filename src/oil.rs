@@ -0,0 +1,290 @@
+use std::fmt;
+
+use rapier3d::prelude::{ContactModificationContext, PhysicsHooks};
+
+use super::lane::{LaneSpec, BOARDS};
+
+// Lane conditioner patterns. A pattern file lists the name, the kind of shot, how far down
+// the lane the oil goes (in feet) and the volume of oil put on every board:
+//
+//   name House Shot
+//   shot house
+//   length 40
+//   boards 4 4 4 ... (39 values, board 1 is at the bowler's right)
+const FEET: f32 = 0.3048;
+//friction between the ball and a dry or a fully oiled board
+const DRY_FRICTION: f32 = 0.2;
+const OILED_FRICTION: f32 = 0.04;
+//from this volume up more oil doesn't make the board any slicker
+const SATURATION: f32 = 40.0;
+//the conditioner runs out over the last stretch of the pattern
+const TAPER: f32 = 2.0 * FEET;
+//highest middle to outside ratio allowed on a sport shot
+const SPORT_RATIO: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shot {
+    // forgiving league pattern, much more oil in the middle than outside
+    House,
+    // flatter competition pattern
+    Sport,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternError {
+    Missing(&'static str),
+    BadValue { key: String, value: String },
+    UnknownKey(String),
+    BoardCount(usize),
+    SportRatio(f32),
+    // a sport shot with a dry board between the oiled ones has no ratio at all
+    SportDryBoard,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Missing(key) => write!(f, "oil pattern has no '{key}'"),
+            PatternError::BadValue { key, value } => write!(f, "bad value '{value}' for '{key}' in oil pattern"),
+            PatternError::UnknownKey(key) => write!(f, "unknown key '{key}' in oil pattern"),
+            PatternError::BoardCount(count) => write!(f, "oil pattern lists {count} boards, a lane has {BOARDS}"),
+            PatternError::SportRatio(ratio) => {
+                write!(f, "a sport shot can't have a ratio above {SPORT_RATIO}:1, this one is {ratio:.1}:1")
+            }
+            PatternError::SportDryBoard => write!(f, "a sport shot can't leave a board dry next to oiled ones"),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OilPattern {
+    pub name: String,
+    pub shot: Shot,
+    // distance from the foul line, in metres
+    pub length: f32,
+    pub boards: [f32; BOARDS],
+}
+
+impl OilPattern {
+    // A lane nobody has oiled
+    pub fn dry() -> Self {
+        Self { name: "Dry".to_string(), shot: Shot::Sport, length: 0.0, boards: [0.0; BOARDS] }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, PatternError> {
+        let mut name = None;
+        let mut shot = None;
+        let mut length = None;
+        let mut boards = None;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            let bad_value = || PatternError::BadValue { key: key.to_string(), value: value.to_string() };
+            match key {
+                "name" => name = Some(value.to_string()),
+                "shot" => {
+                    shot = Some(match value {
+                        "house" => Shot::House,
+                        "sport" => Shot::Sport,
+                        _ => return Err(bad_value()),
+                    })
+                }
+                "length" => {
+                    let feet = value.parse::<f32>().ok().filter(|feet| *feet >= 0.0).ok_or_else(bad_value)?;
+                    length = Some(feet * FEET);
+                }
+                "boards" => {
+                    let volumes = value
+                        .split_whitespace()
+                        .map(|volume| volume.parse::<f32>().ok().filter(|volume| *volume >= 0.0))
+                        .collect::<Option<Vec<f32>>>()
+                        .ok_or_else(bad_value)?;
+                    let count = volumes.len();
+                    boards = Some(volumes.try_into().map_err(|_| PatternError::BoardCount(count))?);
+                }
+                _ => return Err(PatternError::UnknownKey(key.to_string())),
+            }
+        }
+        let pattern = Self {
+            name: name.ok_or(PatternError::Missing("name"))?,
+            shot: shot.ok_or(PatternError::Missing("shot"))?,
+            length: length.ok_or(PatternError::Missing("length"))?,
+            boards: boards.ok_or(PatternError::Missing("boards"))?,
+        };
+        if pattern.shot == Shot::Sport {
+            match pattern.ratio() {
+                Some(ratio) if ratio > SPORT_RATIO => return Err(PatternError::SportRatio(ratio)),
+                Some(_) => {}
+                None => return Err(PatternError::SportDryBoard),
+            }
+        }
+        Ok(pattern)
+    }
+
+    // Oil in the middle against oil outside, leaving out the two boards nearest each gutter.
+    // None when some of those boards are dry and others aren't, there is nothing to divide by
+    pub fn ratio(&self) -> Option<f32> {
+        let inner = &self.boards[2..BOARDS - 2];
+        let most = inner.iter().copied().fold(0.0, f32::max);
+        let least = inner.iter().copied().fold(f32::INFINITY, f32::min);
+        if most == 0.0 {
+            Some(1.0)
+        } else if least == 0.0 {
+            None
+        } else {
+            Some(most / least)
+        }
+    }
+
+    // Oil left on a board (numbered from 1) this far past the foul line
+    pub fn volume_at(&self, board: usize, distance: f32) -> f32 {
+        if distance < 0.0 || distance >= self.length || !(1..=BOARDS).contains(&board) {
+            return 0.0;
+        }
+        let left = ((self.length - distance) / TAPER).min(1.0);
+        self.boards[board - 1] * left
+    }
+}
+
+impl fmt::Display for OilPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shot = match self.shot {
+            Shot::House => "house",
+            Shot::Sport => "sport",
+        };
+        write!(f, "{} ({shot} shot, {:.0} ft, ", self.name, self.length / FEET)?;
+        match self.ratio() {
+            Some(ratio) => write!(f, "{ratio:.1}:1)"),
+            None => write!(f, "dry boards outside)"),
+        }
+    }
+}
+
+// The lane bed as the ball feels it: the friction of every contact with it depends on the oil under it
+pub struct OiledLane {
+    pub pattern: OilPattern,
+    board_width: f32,
+    right_edge: f32,
+}
+
+impl OiledLane {
+    pub fn new(pattern: OilPattern, lane: &LaneSpec) -> Self {
+        Self { pattern, board_width: lane.board_width(), right_edge: -lane.lane_width / 2.0 }
+    }
+
+    // the bowler's right is -x, boards are counted from there
+    pub fn board_at(&self, x: f32) -> usize {
+        let board = ((x - self.right_edge) / self.board_width).floor() as isize + 1;
+        board.clamp(1, BOARDS as isize) as usize
+    }
+
+    pub fn friction_at(&self, x: f32, z: f32) -> f32 {
+        let oil = (self.pattern.volume_at(self.board_at(x), z) / SATURATION).min(1.0);
+        DRY_FRICTION + (OILED_FRICTION - DRY_FRICTION) * oil
+    }
+}
+
+impl PhysicsHooks for OiledLane {
+    fn modify_solver_contacts(&self, context: &mut ContactModificationContext) {
+        for contact in context.solver_contacts.iter_mut() {
+            contact.friction = self.friction_at(contact.point.x, contact.point.z);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(shot: &str, boards: &[f32]) -> Result<OilPattern, PatternError> {
+        let boards: Vec<String> = boards.iter().map(f32::to_string).collect();
+        OilPattern::parse(&format!("name Test\nshot {shot}\nlength 40\nboards {}", boards.join(" ")))
+    }
+
+    // oil on the middle boards and less outside them
+    fn boards(outside: f32, middle: f32) -> [f32; BOARDS] {
+        std::array::from_fn(|board| if (10..29).contains(&board) { middle } else { outside })
+    }
+
+    #[test]
+    fn patterns_parse() {
+        let house = OilPattern::parse(include_str!("../res/patterns/house.txt")).unwrap();
+        assert_eq!((house.name.as_str(), house.shot), ("House Shot", Shot::House));
+        assert_eq!(house.length, 40.0 * FEET);
+        assert_eq!((house.boards[0], house.boards[19]), (4.0, 40.0));
+        assert_eq!(house.ratio(), Some(10.0));
+        let sport = OilPattern::parse(include_str!("../res/patterns/sport.txt")).unwrap();
+        assert_eq!(sport.shot, Shot::Sport);
+        assert!(sport.ratio().unwrap() <= SPORT_RATIO);
+        OilPattern::parse(include_str!("../res/patterns/short.txt")).unwrap();
+        assert_eq!(sport.to_string(), "Sport Shot (sport shot, 44 ft, 2.3:1)");
+    }
+
+    #[test]
+    fn bad_patterns_are_refused() {
+        let bad_value =
+            |key: &str, value: &str| PatternError::BadValue { key: key.to_string(), value: value.to_string() };
+        assert_eq!(pattern("league", &boards(4.0, 4.0)), Err(bad_value("shot", "league")));
+        assert_eq!(pattern("house", &[4.0; 38]), Err(PatternError::BoardCount(38)));
+        assert_eq!(OilPattern::parse("name Test\nshot house\nlength -1"), Err(bad_value("length", "-1")));
+        assert_eq!(OilPattern::parse("name Test\nshot house\nlength 40"), Err(PatternError::Missing("boards")));
+        assert_eq!(OilPattern::parse("name Test\nspeed 3"), Err(PatternError::UnknownKey("speed".to_string())));
+        let boards: Vec<String> =
+            (0..BOARDS).map(|board| if board == 5 { "-1".to_string() } else { "4".to_string() }).collect();
+        let text = format!("name Test\nshot house\nlength 40\nboards {}", boards.join(" "));
+        assert!(matches!(OilPattern::parse(&text), Err(PatternError::BadValue { .. })));
+    }
+
+    #[test]
+    fn ratio_with_dry_boards() {
+        // the two boards at each gutter don't count
+        let mut gutters = boards(10.0, 20.0);
+        gutters[0] = 0.0;
+        gutters[BOARDS - 2] = 0.0;
+        assert_eq!(pattern("sport", &gutters).unwrap().ratio(), Some(2.0));
+        // no oil anywhere is as flat as it gets
+        assert_eq!(OilPattern::dry().ratio(), Some(1.0));
+        assert_eq!(OilPattern::dry().to_string(), "Dry (sport shot, 0 ft, 1.0:1)");
+        // oil in the middle and none outside it has no ratio, a house shot can do that but a sport shot can't
+        let house = pattern("house", &boards(0.0, 20.0)).unwrap();
+        assert_eq!(house.ratio(), None);
+        assert_eq!(house.to_string(), "Test (house shot, 40 ft, dry boards outside)");
+        assert_eq!(pattern("sport", &boards(0.0, 20.0)), Err(PatternError::SportDryBoard));
+        assert_eq!(pattern("sport", &boards(5.0, 20.0)), Err(PatternError::SportRatio(4.0)));
+        assert!(pattern("sport", &boards(5.0, 15.0)).is_ok());
+    }
+
+    #[test]
+    fn oil_runs_out_down_the_lane() {
+        let house = OilPattern::parse(include_str!("../res/patterns/house.txt")).unwrap();
+        assert_eq!(house.volume_at(20, 0.0), 40.0);
+        let tapered = house.volume_at(20, house.length - TAPER / 2.0);
+        assert!((tapered - 20.0).abs() < 1e-3, "{tapered}");
+        assert_eq!(house.volume_at(20, house.length), 0.0);
+        assert_eq!(house.volume_at(20, -0.1), 0.0);
+        assert_eq!((house.volume_at(0, 1.0), house.volume_at(BOARDS + 1, 1.0)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn oiled_lane_friction_follows_the_oil() {
+        let lane = LaneSpec::REGULATION;
+        let house = OilPattern::parse(include_str!("../res/patterns/house.txt")).unwrap();
+        let oiled = OiledLane::new(house, &lane);
+        assert_eq!(oiled.board_at(lane.board_x(1)), 1);
+        assert_eq!(oiled.board_at(lane.board_x(20)), 20);
+        assert_eq!(oiled.board_at(lane.board_x(BOARDS as u8)), BOARDS);
+        // off the lane the edge boards count
+        assert_eq!((oiled.board_at(-lane.lane_width), oiled.board_at(lane.lane_width)), (1, BOARDS));
+        // the middle is saturated, the outside boards have a tenth of the oil
+        let middle = oiled.friction_at(lane.board_x(20), 1.0);
+        let outside = oiled.friction_at(lane.board_x(2), 1.0);
+        assert!((middle - OILED_FRICTION).abs() < 1e-6, "{middle}");
+        assert!((outside - (DRY_FRICTION + (OILED_FRICTION - DRY_FRICTION) * 0.1)).abs() < 1e-6, "{outside}");
+        // past the pattern and on a dry lane the ball hooks
+        assert_eq!(oiled.friction_at(lane.board_x(20), 50.0 * FEET), DRY_FRICTION);
+        let dry = OiledLane::new(OilPattern::dry(), &lane);
+        assert_eq!(dry.friction_at(lane.board_x(20), 1.0), DRY_FRICTION);
+    }
+}
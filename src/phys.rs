@@ -1,12 +1,182 @@
-use std::{fmt::format, ops::Index};
+use std::{fmt::format, ops::Index, num::NonZeroUsize, collections::BTreeMap};
 use cgmath::{self};
 use rapier3d::prelude::*;
 use nalgebra::{Vector3, vector, Vector};
 use winit::event::Force;
+use rapier3d::crossbeam::channel::{unbounded, Receiver};
 
-pub struct PhysicsObj{
-    name: String,
+use super::debug_render;
+use super::lane::{LaneSpec, Surface};
+use super::oil::{OilPattern, OiledLane};
+use super::physics_config::PhysicsConfig;
+use super::pin_shape;
+use super::variant::Variant;
+
+//a pin tilted further than this from vertical is down (~25 degrees)
+const PIN_DOWN_TILT: f32 = 0.44;
+//a pin pushed further than this off its spot is down, even if it stays upright
+const PIN_DOWN_DISPLACEMENT: f32 = 0.15;
+//below these speeds a body counts as resting
+const REST_LINEAR_VELOCITY: f32 = 0.05;
+const REST_ANGULAR_VELOCITY: f32 = 0.1;
+//a fallen pin sliding slower than this can no longer knock anything over
+const DEADWOOD_VELOCITY: f32 = 0.3;
+//simulated seconds after a throw after which the rack counts as settled anyway
+const SETTLE_TIMEOUT: f32 = 10.0;
+const LB_TO_KG: f32 = 0.4536;
+//the ball waits on the approach just behind the foul line
+const BALL_START_Z: f32 = -0.3;
+//release speed in m/s
+const THROW_SPEED: f32 = 8.0;
+const RPM_TO_RAD_PER_SEC: f32 = std::f32::consts::TAU / 60.0;
+//swept pins are parked out of sight below the floor
+const PIN_STORAGE_DEPTH: f32 = -10.0;
+//the bowler is a capsule standing where the camera is
+const BOWLER_HALF_HEIGHT: f32 = 0.7;
+const BOWLER_RADIUS: f32 = 0.2;
+//anything above the foul line counts, however high the bowler jumps
+const FOUL_SENSOR_HEIGHT: f32 = 3.0;
+//where the ball is caught on its way to the pins, just short of the head pin
+const ENTRY_LEAD: f32 = 0.3;
+//farthest apart two bodies that knocked each other in the step can be, a ball at full speed covers this in one
+const NEAREST_CONTACT: f32 = 0.5;
+
+//what the lane sensors saw during a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaneEvent{
+    //the ball dropped into a gutter before reaching the pins
+    GutterBall,
+    //the bowler went past the foul line
+    Foul,
+}
+
+//what touched what, for the sound, the stats and the effects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImpactKind{
+    BallPin(u8),
+    //lower pin number first
+    PinPin(u8, u8),
+    PinGutter(u8),
+    //the ball reached the pit or its back cushion
+    BallPit,
+}
+//two bodies that started touching and how hard they met
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Impact{
+    pub kind: ImpactKind,
+    //N*s, the impulse of the first step the contact pushed
+    pub impulse: f32,
+    //where the contact pushed hardest
+    pub position: Vector3<f32>,
+}
+impl std::fmt::Display for Impact{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        match self.kind{
+            ImpactKind::BallPin(pin) => write!(f, "ball hit pin {pin}")?,
+            ImpactKind::PinPin(a, b) => write!(f, "pin {a} hit pin {b}")?,
+            ImpactKind::PinGutter(pin) => write!(f, "pin {pin} fell in the gutter")?,
+            ImpactKind::BallPit => write!(f, "ball went into the pit")?,
+        }
+        let p = self.position;
+        write!(f, " ({:.2} N*s at {:.2}, {:.2}, {:.2})", self.impulse, p.x, p.y, p.z)
+    }
+}
+//what a collider belongs to
+#[derive(Clone, Copy)]
+enum Part{
+    Ball,
+    Pin(u8),
+    Block(Surface),
+}
+//what an entity is to the game, the renderer picks its model by it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role{
+    Ball,
+    Pin(u8),
+    //the fixed body with every lane block and sensor
+    Lane,
+    //only trips sensors, never drawn
+    Bowler,
+}
+//names one body for as long as it exists, ids are never reused so nothing spawned later takes its place
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntityId(u32);
+struct Entity{
+    role: Role,
+    handle: RigidBodyHandle,
+    //position before the last step, rendering blends it with the current one
+    previous: Option<Isometry<f32>>,
+}
+//How the ball leaves the bowler's hand
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throw{
+    //x of the ball on the approach, positive towards the bowler's left
+    pub position: f32,
+    //m/s
+    pub speed: f32,
+    //degrees from straight down the lane, positive towards the bowler's left
+    pub launch_angle: f32,
+    //revolutions per minute
+    pub rev_rate: f32,
+    //degrees the spin axis is turned from a pure forward roll, positive hooks to the left like a right-hander's ball
+    pub axis_rotation: f32,
+    //degrees the spin axis is tilted up from horizontal, spin around the vertical doesn't grip the lane
+    pub axis_tilt: f32,
+}
+impl Default for Throw{
+    //an average right-handed stroker
+    fn default() -> Self{
+        Self{position: 0.0, speed: THROW_SPEED, launch_angle: 0.0, rev_rate: 300.0, axis_rotation: 45.0, axis_tilt: 15.0}
+    }
+}
+impl Throw{
+    pub fn velocity(&self) -> Vector3<f32>{
+        let (sin, cos) = self.launch_angle.to_radians().sin_cos();
+        vector![sin, 0.0, cos] * self.speed
+    }
+    pub fn angular_velocity(&self) -> Vector3<f32>{
+        let (sin, cos) = self.launch_angle.to_radians().sin_cos();
+        let heading = vector![sin, 0.0, cos];
+        let up = Vector3::y();
+        //a ball rolling straight spins around up x heading, turning the axis back towards the bowler
+        //makes the ball slide sideways on the lane, and the dry boards turn that into hook
+        let (rotation_sin, rotation_cos) = self.axis_rotation.to_radians().sin_cos();
+        let (tilt_sin, tilt_cos) = self.axis_tilt.to_radians().sin_cos();
+        let flat_axis = up.cross(&heading) * rotation_cos - heading * rotation_sin;
+        let axis = flat_axis * tilt_cos + up * tilt_sin;
+        axis * self.rev_rate * RPM_TO_RAD_PER_SEC
+    }
+}
+//Set of pins by their number (1 is the head pin), stored as a bitmask
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PinSet(u16);
+impl PinSet{
+    pub fn contains(self, pin: u8) -> bool{
+        self.0 & (1 << pin) != 0
+    }
+    pub fn insert(&mut self, pin: u8){
+        self.0 |= 1 << pin;
+    }
+    pub fn remove(&mut self, pin: u8){
+        self.0 &= !(1 << pin);
+    }
+    pub fn len(self) -> u8{
+        self.0.count_ones() as u8
+    }
+    pub fn iter(self) -> impl Iterator<Item = u8>{
+        (1..16).filter(move |&pin| self.contains(pin))
+    }
+}
+impl std::fmt::Display for PinSet{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        let pins: Vec<String> = self.iter().map(|pin| pin.to_string()).collect();
+        write!(f, "{}", pins.join("-"))
+    }
+}
+struct Pin{
+    number: u8,
     handle: RigidBodyHandle,
+    spot: Vector3<f32>,
 }
 pub struct Physics{
     gravity: Vector3<f32>,
@@ -21,11 +191,40 @@ pub struct Physics{
     ccd_solver: CCDSolver,
     island_manager: IslandManager,
     query_pipeline: QueryPipeline,
-    physics_obj: Vec<PhysicsObj>
+    //ordered by id, so they are stepped through the same way every run
+    entities: BTreeMap<EntityId, Entity>,
+    next_entity: u32,
+    lane: LaneSpec,
+    //the variant the game is played with, its sizes are the config's where that gives its own
+    picked: Variant,
+    //rack, pin and ball geometry
+    variant: Variant,
+    //contacts with the lane bed take their friction from the oil on it
+    oil: OiledLane,
+    pins: Vec<Pin>,
+    //sensor colliders and what it means when they are tripped
+    sensors: Vec<(ColliderHandle, LaneEvent)>,
+    event_handler: ChannelEventCollector,
+    collision_events: Receiver<CollisionEvent>,
+    contact_force_events: Receiver<ContactForceEvent>,
+    //contacts that started but haven't pushed yet, the narrow phase reports them a little ahead
+    touching: Vec<(ColliderHandle, ColliderHandle, ImpactKind)>,
+    //what hit what during the last step
+    impacts: Vec<Impact>,
+    lane_events: Vec<LaneEvent>,
+    //simulated time, advanced by every step
+    sim_time: f32,
+    throw_time: Option<f32>,
+    //where the ball of the current throw reached the pin deck
+    ball_entry: Option<Vector3<f32>>,
+    config: PhysicsConfig,
+    //lane colliders and what they are made of
+    blocks: Vec<(ColliderHandle, Surface)>,
 }
 impl Physics{
-    pub fn new() -> Self{
-        let gravity = vector![0.0, -9.81, 0.0];
+    pub fn new(lane: LaneSpec) -> Self{
+        let config = PhysicsConfig::default();
+        let gravity = vector![0.0, -config.gravity, 0.0];
         let physics_pipeline = PhysicsPipeline::new();
         let island_manager = IslandManager::new();
         let bodies = RigidBodySet::new();
@@ -36,9 +235,14 @@ impl Physics{
         let multibody_joints = MultibodyJointSet::new();
         let ccd_solver = CCDSolver::new();
         let island_manager = IslandManager::new();
-        let integration_params=IntegrationParameters::default();
+        let integration_params = IntegrationParameters{
+            num_solver_iterations: NonZeroUsize::new(config.solver_iterations).unwrap_or(NonZeroUsize::MIN),
+            ..IntegrationParameters::default()
+        };
         let query_pipeline = QueryPipeline::new();
-        let physics_obj: Vec<PhysicsObj> = Vec::new();
+        let (collision_send, collision_events) = unbounded();
+        let (contact_force_send, contact_force_events) = unbounded();
+        let event_handler = ChannelEventCollector::new(collision_send, contact_force_send);
         Self { 
             gravity,
             physics_pipeline,
@@ -52,10 +256,31 @@ impl Physics{
             integration_params,
             island_manager,
             query_pipeline,
-            physics_obj
+            entities: BTreeMap::new(),
+            next_entity: 0,
+            oil: OiledLane::new(OilPattern::dry(), &lane),
+            lane,
+            picked: Variant::default(),
+            variant: Variant::default(),
+            pins: Vec::new(),
+            sensors: Vec::new(),
+            event_handler,
+            collision_events,
+            contact_force_events,
+            touching: Vec::new(),
+            impacts: Vec::new(),
+            lane_events: Vec::new(),
+            sim_time: 0.0,
+            throw_time: None,
+            ball_entry: None,
+            config,
+            blocks: Vec::new(),
         }   
     }
     pub fn simulate(&mut self){
+        for entity in self.entities.values_mut(){
+            entity.previous = Some(*self.bodies[entity.handle].position());
+        }
         self.physics_pipeline.step(
             &self.gravity,
             &self.integration_params,
@@ -68,88 +293,747 @@ impl Physics{
             &mut self.multibody_joints,
             &mut self.ccd_solver,
             Some(&mut self.query_pipeline),
-            &(),
-            &(),
+            &self.oil,
+            &self.event_handler,
         );
-        
-    }
-    pub fn get_translation(&mut self,index: usize) ->[f32;3]{
-        
-        let trans_obj = &self.bodies.get(self.physics_obj[index].handle).unwrap().translation();
-        let translation =  [trans_obj.x,trans_obj.y,trans_obj.z];
-        //if(index == 1) {println!("Position: {},{},{}",trans_obj.x,trans_obj.y,trans_obj.z)};
-        translation
-        
-    }
-    pub fn get_rotation(&mut self,index: usize) ->[f32;3]{
-        let rotation_obj = &self.bodies.get(self.physics_obj[index].handle).unwrap().rotation();
-        let rotation = [rotation_obj.i,rotation_obj.j,rotation_obj.k] ;
-        //if(index == 1) {println!("Rotation{},{},{}",rotation_obj.i,rotation_obj.j,rotation_obj.k)};
-        rotation
-    }
-    pub fn throw_ball(&mut self,force:Vector3<f32>){
-        println!("{}",self.physics_obj[0].name);
-        //ball is always on index 0
-        let body = self.bodies.get_mut(self.physics_obj[0].handle).unwrap();
-        body.apply_impulse(force*0.2, true);
+        self.sim_time += self.integration_params.dt;
+        if let (Some(_), None, Some(handle)) = (self.throw_time, self.ball_entry, self.handle(Role::Ball)){
+            let ball = *self.bodies[handle].translation();
+            if ball.z >= self.lane.foul_line_to_head_pin - ENTRY_LEAD{
+                self.ball_entry = Some(ball);
+            }
+        }
+        self.impacts.clear();
+        let mut stopped = Vec::new();
+        while let Ok(event) = self.collision_events.try_recv(){
+            match event{
+                CollisionEvent::Started(a, b, flags) if flags.contains(CollisionEventFlags::SENSOR) => {
+                    self.handle_sensor(a, b);
+                    self.handle_sensor(b, a);
+                }
+                CollisionEvent::Started(a, b, _) => {
+                    if let Some(kind) = self.impact_kind(a, b){
+                        self.touching.push((a, b, kind));
+                    }
+                }
+                CollisionEvent::Stopped(a, b, _) => stopped.push((a, b)),
+            }
+        }
+        //a quick knock can start and end within the step that pushed, its force still counts
+        while let Ok(event) = self.contact_force_events.try_recv(){
+            let pair = (event.collider1, event.collider2);
+            let Some(index) = self.touching.iter().position(|&(a, b, _)| same_pair(pair, (a, b))) else { continue };
+            let (_, _, kind) = self.touching.swap_remove(index);
+            let position = self.contact_point(pair.0, pair.1).unwrap_or_else(|| *self.colliders[pair.0].translation());
+            self.impacts.push(Impact{kind, impulse: event.total_force_magnitude * self.integration_params.dt, position});
+        }
+        for pair in stopped{
+            self.touching.retain(|&(a, b, _)| !same_pair(pair, (a, b)));
+        }
+    }
+    //draws what the simulation sees, and a line for the velocity of every awake body
+    pub fn debug_render(&self, pipeline: &mut DebugRenderPipeline, backend: &mut impl DebugRenderBackend){
+        pipeline.render(backend, &self.bodies, &self.colliders, &self.impulse_joints, &self.multibody_joints, &self.narrow_phase);
+        for (handle, body) in self.bodies.iter().filter(|(_, body)| body.is_dynamic() && body.is_enabled() && !body.is_sleeping()){
+            let from = Point::from(*body.translation());
+            let to = from + body.linvel() * debug_render::VELOCITY_SCALE;
+            backend.draw_line(DebugRenderObject::RigidBody(handle, body), from, to, debug_render::VELOCITY_COLOR);
+        }
+    }
+    //the impacts of the last step, replaced by the next one
+    pub fn impacts(&self) -> &[Impact]{
+        &self.impacts
+    }
+    fn part(&self, collider: ColliderHandle) -> Option<Part>{
+        if let Some(&(_, surface)) = self.blocks.iter().find(|(handle, _)| *handle == collider){
+            return Some(Part::Block(surface));
+        }
+        match self.role_of(self.colliders.get(collider)?.parent()?)?{
+            Role::Ball => Some(Part::Ball),
+            Role::Pin(number) => Some(Part::Pin(number)),
+            Role::Lane | Role::Bowler => None,
+        }
+    }
+    fn impact_kind(&self, a: ColliderHandle, b: ColliderHandle) -> Option<ImpactKind>{
+        match (self.part(a)?, self.part(b)?){
+            (Part::Ball, Part::Pin(pin)) | (Part::Pin(pin), Part::Ball) => Some(ImpactKind::BallPin(pin)),
+            (Part::Pin(a), Part::Pin(b)) => Some(ImpactKind::PinPin(a.min(b), a.max(b))),
+            (Part::Pin(pin), Part::Block(Surface::Gutter)) | (Part::Block(Surface::Gutter), Part::Pin(pin)) => {
+                Some(ImpactKind::PinGutter(pin))
+            }
+            (Part::Ball, Part::Block(Surface::Pit | Surface::Cushion)) | (Part::Block(Surface::Pit | Surface::Cushion), Part::Ball) => {
+                Some(ImpactKind::BallPit)
+            }
+            _ => None,
+        }
+    }
+    //the contact point of the pair that carried the largest impulse, in world coordinates. A hard knock can push the
+    //bodies apart within the step, then there are no points left and it is halfway between their nearest points
+    fn contact_point(&self, a: ColliderHandle, b: ColliderHandle) -> Option<Vector3<f32>>{
+        let pair = self.narrow_phase.contact_pair(a, b)?;
+        let point = pair.manifolds.iter()
+            .flat_map(|manifold| manifold.points.iter())
+            .max_by(|p, q| p.data.impulse.total_cmp(&q.data.impulse));
+        if let Some(point) = point{
+            return Some((self.colliders[pair.collider1].position() * point.local_p1).coords);
+        }
+        let (a, b) = (&self.colliders[a], &self.colliders[b]);
+        let nearest = rapier3d::parry::query::contact(a.position(), a.shape(), b.position(), b.shape(), NEAREST_CONTACT).ok()??;
+        Some(nalgebra::center(&nearest.point1, &nearest.point2).coords)
+    }
+    //only the ball trips the gutters and only the bowler trips the foul line
+    fn handle_sensor(&mut self, sensor: ColliderHandle, other: ColliderHandle){
+        let Some(&(_, event)) = self.sensors.iter().find(|(handle, _)| *handle == sensor) else { return };
+        let Some(body) = self.colliders.get(other).and_then(|collider| collider.parent()) else { return };
+        let tripped_by = match event{
+            LaneEvent::GutterBall => Role::Ball,
+            LaneEvent::Foul => Role::Bowler,
+        };
+        if self.role_of(body) == Some(tripped_by){
+            self.lane_events.push(event);
+        }
+    }
+    pub fn drain_lane_events(&mut self) -> Vec<LaneEvent>{
+        std::mem::take(&mut self.lane_events)
+    }
+    //the bowler stands on the floor below the given point
+    pub fn move_bowler(&mut self, position: Vector3<f32>){
+        if let Some(body) = self.handle(Role::Bowler).and_then(|handle| self.bodies.get_mut(handle)){
+            body.set_next_kinematic_translation(vector![position.x, BOWLER_HALF_HEIGHT + BOWLER_RADIUS, position.z]);
+        }
+    }
+    //no event fires for a bowler who was already over the line before the throw
+    pub fn bowler_over_foul_line(&self) -> bool{
+        let Some(body) = self.handle(Role::Bowler).map(|handle| &self.bodies[handle]) else { return false };
+        self.sensors.iter()
+            .filter(|(_, event)| *event == LaneEvent::Foul)
+            .any(|(sensor, _)| body.colliders().iter()
+                .any(|&collider| self.narrow_phase.intersection_pair(collider, *sensor) == Some(true)))
+    }
+    //position of an entity between the last two steps, alpha 0 is the one before the last step and 1 the last one
+    fn interpolated(&self, entity: EntityId, alpha: f32) -> Option<Isometry<f32>>{
+        let entity = self.entities.get(&entity)?;
+        let current = *self.bodies[entity.handle].position();
+        Some(match entity.previous{
+            Some(previous) => previous.lerp_slerp(&current, alpha.clamp(0.0, 1.0)),
+            None => current,
+        })
+    }
+    pub fn get_translation(&self, entity: EntityId, alpha: f32) -> Option<[f32;3]>{
+        let trans_obj = self.interpolated(entity, alpha)?.translation.vector;
+        Some([trans_obj.x,trans_obj.y,trans_obj.z])
+    }
+    //euler angles in the order the model matrix applies them, x first and z last
+    pub fn get_rotation(&self, entity: EntityId, alpha: f32) -> Option<[f32;3]>{
+        let (roll, pitch, yaw) = self.interpolated(entity, alpha)?.rotation.euler_angles();
+        Some([roll, pitch, yaw])
+    }
+    pub fn entities(&self) -> impl Iterator<Item = (EntityId, Role)> + '_{
+        self.entities.iter().map(|(&id, entity)| (id, entity.role))
+    }
+    //the first spawned of the role that is still there, it is the one that plays
+    pub fn entity(&self, role: Role) -> Option<EntityId>{
+        self.entities.iter().find(|(_, entity)| entity.role == role).map(|(&id, _)| id)
+    }
+    fn handle(&self, role: Role) -> Option<RigidBodyHandle>{
+        self.entity(role).map(|id| self.entities[&id].handle)
+    }
+    fn role_of(&self, body: RigidBodyHandle) -> Option<Role>{
+        let id = EntityId(self.bodies.get(body)?.user_data as u32);
+        self.entities.get(&id).filter(|entity| entity.handle == body).map(|entity| entity.role)
+    }
+    //a body put somewhere else starts there, rendering must not slide it over from its old pose
+    fn teleported(&mut self, body: RigidBodyHandle){
+        let position = *self.bodies[body].position();
+        let id = EntityId(self.bodies[body].user_data as u32);
+        if let Some(entity) = self.entities.get_mut(&id).filter(|entity| entity.handle == body){
+            entity.previous = Some(position);
+        }
+    }
+    pub fn lane(&self) -> &LaneSpec{
+        &self.lane
+    }
+    //has to be picked before the colliders are built
+    pub fn set_variant(&mut self, variant: Variant){
+        self.variant = self.config.sized(&variant);
+        self.picked = variant;
+    }
+    //the variant as it is built, with the sizes of the config
+    pub fn variant(&self) -> &Variant{
+        &self.variant
+    }
+    fn ball_start(&self, x: f32) -> Vector3<f32>{
+        vector![x, self.variant.ball.radius, BALL_START_Z]
+    }
+    pub fn oil_pattern(&self) -> &OilPattern{
+        &self.oil.pattern
+    }
+    pub fn set_oil_pattern(&mut self, pattern: OilPattern){
+        self.oil = OiledLane::new(pattern, &self.lane);
+    }
+    pub fn throw_ball(&mut self,throw: &Throw){
+        let start = self.ball_start(throw.position);
+        let Some(handle) = self.handle(Role::Ball) else { return };
+        let body = &mut self.bodies[handle];
+        body.set_translation(start, false);
+        body.set_linvel(throw.velocity(), true);
+        body.set_angvel(throw.angular_velocity(), true);
+        self.teleported(handle);
+        self.throw_time = Some(self.sim_time);
+        self.ball_entry = None;
+    }
+    //None until the ball of the last throw got to the pins
+    pub fn ball_entry(&self) -> Option<Vector3<f32>>{
+        self.ball_entry
+    }
+    fn pin_is_down(&self, pin: &Pin) -> bool{
+        let body = &self.bodies[pin.handle];
+        let up = body.rotation() * Vector3::y();
+        let tilt = up.y.clamp(-1.0, 1.0).acos();
+        let displacement = (body.translation() - pin.spot).norm();
+        tilt > PIN_DOWN_TILT || displacement > PIN_DOWN_DISPLACEMENT
+    }
+    pub fn standing_pins(&self) -> PinSet{
+        let mut standing = PinSet::default();
+        for pin in self.pins.iter().filter(|pin| !self.pin_is_down(pin)){
+            standing.insert(pin.number);
+        }
+        standing
+    }
+    fn is_resting(body: &RigidBody) -> bool{
+        body.is_sleeping()
+            || (body.linvel().norm() < REST_LINEAR_VELOCITY
+                && body.angvel().norm() < REST_ANGULAR_VELOCITY)
+    }
+    //true as soon as the ball has disturbed any pin on the deck
+    pub fn pins_in_motion(&self) -> bool{
+        self.pins.iter()
+            .map(|pin| &self.bodies[pin.handle])
+            .any(|body| body.is_dynamic() && body.is_enabled() && !Self::is_resting(body))
+    }
+    //true once every body has come to rest, or the throw has timed out
+    pub fn is_settled(&self) -> bool{
+        if let Some(throw_time) = self.throw_time{
+            if self.sim_time - throw_time > SETTLE_TIMEOUT{
+                return true;
+            }
+        }
+        //whatever ended up in the pit no longer matters
+        let pit = self.lane.lane_length();
+        let on_lane = |body: &RigidBody| body.is_dynamic() && body.is_enabled() && body.translation().z < pit;
+        if let Some(ball) = self.handle(Role::Ball).map(|handle| &self.bodies[handle]){
+            if on_lane(ball) && !Self::is_resting(ball){
+                return false;
+            }
+        }
+        //deadwood lying on the deck tends to creep, only care about it while it moves fast enough to take out a pin
+        self.pins.iter().all(|pin| {
+            let body = &self.bodies[pin.handle];
+            !on_lane(body)
+                || Self::is_resting(body)
+                || (self.pin_is_down(pin) && body.linvel().norm() < DEADWOOD_VELOCITY)
+        })
+    }
+    //pins knocked down, reported only once the rack has settled
+    pub fn fallen_pins(&self) -> Option<PinSet>{
+        if !self.is_settled(){
+            return None;
+        }
+        let mut fallen = PinSet::default();
+        for pin in self.pins.iter().filter(|pin| self.pin_is_down(pin)){
+            fallen.insert(pin.number);
+        }
+        Some(fallen)
+    }
+    pub fn finish_throw(&mut self){
+        self.throw_time = None;
+    }
+    pub fn timestep(&self) -> f32{
+        self.integration_params.dt
+    }
+    //the config gives the ball or the pins other sizes, they have to be built again for it
+    pub fn resizes(&self, config: &PhysicsConfig) -> bool{
+        config.sized(&self.picked) != self.variant
+    }
+    //takes effect right away on everything already built, in the middle of a throw too;
+    //new sizes build the ball and the pins again, the pins that stood stand up again on their spots
+    pub fn set_config(&mut self, config: PhysicsConfig){
+        if self.resizes(&config){
+            self.variant = config.sized(&self.picked);
+            self.config = config.clone();
+            if !self.entities.is_empty(){
+                let standing = self.standing_pins();
+                self.build_colliders();
+                self.set_up_leave(standing);
+            }
+        }
+        self.gravity = vector![0.0, -config.gravity, 0.0];
+        self.integration_params.num_solver_iterations = NonZeroUsize::new(config.solver_iterations).unwrap_or(NonZeroUsize::MIN);
+        let ball = self.handle(Role::Ball).map(|handle| (handle, config.ball));
+        let pins = self.pins.iter().map(|pin| (pin.handle, config.pin));
+        for (handle, body_config) in ball.into_iter().chain(pins){
+            let body = &mut self.bodies[handle];
+            body.set_linear_damping(body_config.linear_damping);
+            body.set_angular_damping(body_config.angular_damping);
+            for &collider in body.colliders(){
+                self.colliders[collider].set_friction(body_config.material.friction);
+                self.colliders[collider].set_restitution(body_config.material.restitution);
+            }
+        }
+        let pin_mass = pin_shape::pin_mass_properties(&self.variant.pin, config.pin_center_of_mass);
+        for pin in &self.pins{
+            for &collider in self.bodies[pin.handle].colliders(){
+                self.colliders[collider].set_mass_properties(pin_mass);
+            }
+        }
+        for &(handle, surface) in &self.blocks{
+            let material = config.surface(surface);
+            self.colliders[handle].set_friction(material.friction);
+            self.colliders[handle].set_restitution(material.restitution);
+        }
+        self.config = config;
+    }
+    pub fn set_timestep(&mut self, dt: f32){
+        self.integration_params.dt = dt;
+    }
+    pub fn all_pins(&self) -> PinSet{
+        let mut all = PinSet::default();
+        for pin in &self.pins{
+            all.insert(pin.number);
+        }
+        all
+    }
+    fn pin_handle(&self, number: u8) -> Option<RigidBodyHandle>{
+        self.pins.iter().find(|pin| pin.number == number).map(|pin| pin.handle)
+    }
+    pub fn pin_spot(&self, number: u8) -> Option<Vector3<f32>>{
+        self.pins.iter().find(|pin| pin.number == number).map(|pin| pin.spot)
+    }
+    pub fn pin_translation(&self, number: u8) -> Option<Vector3<f32>>{
+        self.pin_handle(number).map(|handle| *self.bodies[handle].translation())
+    }
+    //pinsetter deck: holds a pin still in the air above the lane
+    pub fn lift_pin(&mut self, number: u8, height: f32){
+        let Some(handle) = self.pin_handle(number) else { return };
+        let half_height = self.variant.pin.half_height;
+        let body = &mut self.bodies[handle];
+        let mut position = *body.translation();
+        position.y = half_height + height;
+        body.set_body_type(RigidBodyType::KinematicPositionBased, true);
+        body.set_position(Isometry::translation(position.x, position.y, position.z), true);
+        self.teleported(handle);
+    }
+    //lowers a pin upright onto the deck at the given spot
+    pub fn place_pin(&mut self, number: u8, position: Vector3<f32>){
+        let Some(handle) = self.pin_handle(number) else { return };
+        let half_height = self.variant.pin.half_height;
+        let body = &mut self.bodies[handle];
+        body.set_body_type(RigidBodyType::Dynamic, true);
+        body.set_enabled(true);
+        body.set_position(Isometry::translation(position.x, half_height, position.z), true);
+        body.set_linvel(Vector3::zeros(), false);
+        body.set_angvel(Vector3::zeros(), false);
+        body.sleep();
+        self.teleported(handle);
+    }
+    //sweeps a pin off the deck
+    pub fn remove_pin(&mut self, number: u8){
+        let Some(handle) = self.pin_handle(number) else { return };
+        let body = &mut self.bodies[handle];
+        body.set_body_type(RigidBodyType::Dynamic, true);
+        body.set_position(Isometry::translation(number as f32, PIN_STORAGE_DEPTH, 0.0), false);
+        body.set_linvel(Vector3::zeros(), false);
+        body.set_angvel(Vector3::zeros(), false);
+        body.set_enabled(false);
+        self.teleported(handle);
+    }
+    pub fn rerack(&mut self){
+        let spots: Vec<(u8, Vector3<f32>)> = self.pins.iter().map(|pin| (pin.number, pin.spot)).collect();
+        for (number, spot) in spots{
+            self.place_pin(number, spot);
+        }
+    }
+    //practice rack: only the given pins stand on their spots, the rest stay under the deck
+    pub fn set_up_leave(&mut self, leave: PinSet){
+        let spots: Vec<(u8, Vector3<f32>)> = self.pins.iter().map(|pin| (pin.number, pin.spot)).collect();
+        for (number, spot) in spots{
+            if leave.contains(number){
+                self.place_pin(number, spot);
+            }else{
+                self.remove_pin(number);
+            }
+        }
+    }
+    //pins on the deck as they stand or lie, pins swept off it are left out
+    pub fn pins_on_deck(&self) -> Vec<(u8, Isometry<f32>)>{
+        self.pins.iter()
+            .filter(|pin| self.bodies[pin.handle].is_enabled())
+            .map(|pin| (pin.number, *self.bodies[pin.handle].position()))
+            .collect()
+    }
+    //puts the given pins back exactly where they were, the rest go under the deck
+    pub fn restore_pins(&mut self, pins: &[(u8, Isometry<f32>)]){
+        let numbers: Vec<u8> = self.pins.iter().map(|pin| pin.number).collect();
+        for number in numbers{
+            self.remove_pin(number);
+            let Some(&(_, position)) = pins.iter().find(|(pin, _)| *pin == number) else { continue };
+            let Some(handle) = self.pin_handle(number) else { continue };
+            let body = &mut self.bodies[handle];
+            body.set_enabled(true);
+            body.set_position(position, true);
+            body.sleep();
+            self.teleported(handle);
+        }
+    }
+    //ball return: puts the ball back on the approach
+    pub fn return_ball(&mut self){
+        let start = self.ball_start(0.0);
+        let Some(handle) = self.handle(Role::Ball) else { return };
+        let body = &mut self.bodies[handle];
+        body.set_position(Isometry::new(start, Vector3::zeros()), false);
+        body.set_linvel(Vector3::zeros(), false);
+        body.set_angvel(Vector3::zeros(), false);
+        body.sleep();
+        self.teleported(handle);
+    }
+    //the bowler carries the ball across the approach before the throw
+    pub fn place_ball(&mut self, x: f32){
+        let start = self.ball_start(x);
+        let Some(handle) = self.handle(Role::Ball) else { return };
+        let body = &mut self.bodies[handle];
+        body.set_translation(start, false);
+        body.sleep();
+        self.teleported(handle);
+    }
+    //a ball density in the config outweighs the bowler's pick
+    pub fn set_ball_weight(&mut self, weight_lb: f32){
+        let Some(handle) = self.handle(Role::Ball) else { return };
+        let weight_lb = self.variant.ball.weight_lb.filter(|_| self.config.ball.density.is_some()).unwrap_or(weight_lb);
+        for &handle in self.bodies[handle].colliders(){
+            self.colliders[handle].set_mass(weight_lb * LB_TO_KG);
+        }
     }
     pub fn reset_ball_vel(&mut self){
-        let body = self.bodies.get_mut(self.physics_obj[0].handle).unwrap();
+        let Some(handle) = self.handle(Role::Ball) else { return };
         //it's waken up when we throw the ball again
-        body.sleep();
+        self.bodies[handle].sleep();
     }
+    //the ball, the rack of the variant, the lane and the bowler, in place of whatever was built before
     pub fn build_colliders(&mut self){
-
+        for id in self.entities.keys().copied().collect::<Vec<_>>(){
+            self.despawn(id);
+        }
+        self.spawn(Role::Ball);
+        for number in self.variant.rack.iter().map(|spot| spot.number).collect::<Vec<_>>(){
+            self.spawn(Role::Pin(number));
+        }
+        self.spawn(Role::Lane);
+        self.spawn(Role::Bowler);
+    }
+    //builds another body of the role next to the ones already there; None for a pin the rack has no spot for
+    //or one that is there already, pins are counted and saved by their number
+    pub fn spawn(&mut self, role: Role) -> Option<EntityId>{
+        if let Role::Pin(number) = role{
+            self.variant.rack.iter().find(|spot| spot.number == number)?;
+            if self.pin_handle(number).is_some(){
+                return None;
+            }
+        }
+        let id = EntityId(self.next_entity);
+        self.next_entity += 1;
+        let handle = match role{
+            Role::Ball => self.build_ball(),
+            Role::Pin(number) => self.build_pin(number),
+            Role::Lane => self.build_lane(),
+            Role::Bowler => self.build_bowler(),
+        };
+        self.bodies[handle].user_data = id.0 as u128;
+        self.entities.insert(id, Entity{role, handle, previous: None});
+        Some(id)
+    }
+    //removes the body with its colliders, returns what it was
+    pub fn despawn(&mut self, id: EntityId) -> Option<Role>{
+        let entity = self.entities.remove(&id)?;
+        let body = self.bodies.remove(
+            entity.handle,
+            &mut self.island_manager,
+            &mut self.colliders,
+            &mut self.impulse_joints,
+            &mut self.multibody_joints,
+            true,
+        )?;
+        let colliders = body.colliders();
+        self.blocks.retain(|(handle, _)| !colliders.contains(handle));
+        self.sensors.retain(|(handle, _)| !colliders.contains(handle));
+        self.touching.retain(|(a, b, _)| !colliders.contains(a) && !colliders.contains(b));
+        self.pins.retain(|pin| pin.handle != entity.handle);
+        Some(entity.role)
+    }
+    fn build_ball(&mut self) -> RigidBodyHandle{
+        let ball = self.variant.ball;
+        let config = &self.config;
         let bowling_body = RigidBodyBuilder::dynamic()
-        .translation(vector![-4.5,1.0,4.5])
+        .translation(self.ball_start(0.0))
         .sleeping(true)
-        .linear_damping(0.01)
-        .angular_damping(0.01)
+        .ccd_enabled(true)
+        .linear_damping(config.ball.linear_damping)
+        .angular_damping(config.ball.angular_damping)
         .build();
         let bowling_handle = self.bodies.insert(bowling_body);
-        self.physics_obj.push(PhysicsObj{name: "bowling_ball".to_string(),handle: bowling_handle});
-        let bowling_collider = ColliderBuilder::ball(0.2)
-        .restitution(0.1)
-        .density(10.0)
-        .friction(0.5)
+        let bowling_collider = ColliderBuilder::ball(ball.radius)
+        .restitution(config.ball.material.restitution)
+        .mass(ball.weight_lb.unwrap_or(14.0) * LB_TO_KG)
+        .friction(config.ball.material.friction)
+        .active_events(ActiveEvents::COLLISION_EVENTS | ActiveEvents::CONTACT_FORCE_EVENTS)
         .build();
         self.colliders.insert_with_parent(bowling_collider, bowling_handle, &mut self.bodies);
-        
-        // Create pins (10 pins in triangle formation)
-        for row in 0..5 {
-            let z = row as f32 * 0.8;
-            let x_off=-(row as f32 )* 0.8 /2.0;
-            for col in 0..row{
-                let x = x_off + col as f32 * 0.8;
-                let pin_body = RigidBodyBuilder::dynamic()
-                    .translation(vector![x, 0.0, z])
-                    .angular_damping(1.0)
-                    .linear_damping(1.0)
-                    //.sleeping(true)
-                    .build();
-            let pin_handle = self.bodies.insert(pin_body);
-            self.physics_obj.push(PhysicsObj{name: format!("Pin{row},{col}"),handle: pin_handle});
-            let pin_collider = ColliderBuilder::cylinder(0.38, 0.05)
-                .restitution(0.9)
-                .friction(0.4)
-                .density(2.0)
+        bowling_handle
+    }
+    //the pin stands on its spot of the variant's rack, the front pin on the lane's centre line
+    fn build_pin(&mut self, number: u8) -> RigidBodyHandle{
+        let pin = self.variant.pin;
+        let config = &self.config;
+        let rack_spot = self.variant.rack.iter().find(|spot| spot.number == number).expect("pins are spawned on the rack");
+        let spot = vector![rack_spot.x, pin.half_height, self.lane.foul_line_to_head_pin + rack_spot.depth];
+        let pin_body = RigidBodyBuilder::dynamic()
+            .translation(spot)
+            .ccd_enabled(true)
+            .angular_damping(config.pin.angular_damping)
+            .linear_damping(config.pin.linear_damping)
+            //.sleeping(true)
+            .build();
+        let pin_handle = self.bodies.insert(pin_body);
+        let pin_collider = pin_shape::pin_collider(&pin, config.pin_center_of_mass)
+            .restitution(config.pin.material.restitution)
+            .friction(config.pin.material.friction)
+            .active_events(ActiveEvents::COLLISION_EVENTS | ActiveEvents::CONTACT_FORCE_EVENTS)
+            .build();
+        self.colliders.insert_with_parent(pin_collider, pin_handle, &mut self.bodies);
+        self.pins.push(Pin{number, handle: pin_handle, spot});
+        pin_handle
+    }
+    fn build_lane(&mut self) -> RigidBodyHandle{
+        //the lane is one fixed body made of blocks, the same ones the lane mesh is built from
+        let lane_body = RigidBodyBuilder::fixed().build();
+        let lane_handle = self.bodies.insert(lane_body);
+        for block in self.lane.blocks(){
+            let material = self.config.surface(block.surface);
+            let [hx, hy, hz] = block.half_extents;
+            //the oil pattern decides the friction on the lane bed
+            let hooks = if block.surface == Surface::Lane { ActiveHooks::MODIFY_SOLVER_CONTACTS } else { ActiveHooks::empty() };
+            let collider = ColliderBuilder::cuboid(hx, hy, hz)
+                .translation(Vector3::from(block.center))
+                .friction(material.friction)
+                .restitution(material.restitution)
+                .active_hooks(hooks)
                 .build();
-            self.colliders.insert_with_parent(pin_collider, pin_handle, &mut self.bodies);
-        
-            }
+            let handle = self.colliders.insert_with_parent(collider, lane_handle, &mut self.bodies);
+            self.blocks.push((handle, block.surface));
+        }
+
+        //gutter sensors sit in the bottom of the channel, a ball riding the edge of the lane doesn't reach them
+        let gutter_depth = self.lane.gutter_depth;
+        let gutter_x = self.lane.lane_width / 2.0 + self.lane.gutter_width / 2.0;
+        let head_pin = self.lane.foul_line_to_head_pin;
+        for side in [-1.0, 1.0]{
+            let sensor = ColliderBuilder::cuboid(self.lane.gutter_width / 2.0, gutter_depth / 4.0, head_pin / 2.0)
+                .translation(vector![side * gutter_x, -gutter_depth * 0.75, head_pin / 2.0])
+                .sensor(true)
+                .active_events(ActiveEvents::COLLISION_EVENTS)
+                .build();
+            let handle = self.colliders.insert_with_parent(sensor, lane_handle, &mut self.bodies);
+            self.sensors.push((handle, LaneEvent::GutterBall));
+        }
+        //everything past the foul line, the bowler is kinematic so the sensor has to look for it
+        let half_width = self.lane.half_width() + self.lane.wall_thickness;
+        let length = self.lane.lane_length();
+        let foul_sensor = ColliderBuilder::cuboid(half_width, FOUL_SENSOR_HEIGHT / 2.0, length / 2.0)
+            .translation(vector![0.0, FOUL_SENSOR_HEIGHT / 2.0, length / 2.0])
+            .sensor(true)
+            .active_events(ActiveEvents::COLLISION_EVENTS)
+            .active_collision_types(ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_FIXED)
+            .build();
+        let handle = self.colliders.insert_with_parent(foul_sensor, lane_handle, &mut self.bodies);
+        self.sensors.push((handle, LaneEvent::Foul));
+        lane_handle
+    }
+    fn build_bowler(&mut self) -> RigidBodyHandle{
+        //the bowler only trips sensors, the ball and pins pass right through
+        let bowler_body = RigidBodyBuilder::kinematic_position_based()
+            .translation(vector![0.0, BOWLER_HALF_HEIGHT + BOWLER_RADIUS, -1.0])
+            .build();
+        let bowler = self.bodies.insert(bowler_body);
+        let bowler_collider = ColliderBuilder::capsule_y(BOWLER_HALF_HEIGHT, BOWLER_RADIUS)
+            .solver_groups(InteractionGroups::none())
+            .build();
+        self.colliders.insert_with_parent(bowler_collider, bowler, &mut self.bodies);
+        bowler
+    }
+}
+//rapier gives the colliders of a pair in either order
+fn same_pair(a: (ColliderHandle, ColliderHandle), b: (ColliderHandle, ColliderHandle)) -> bool{
+    a == b || a == (b.1, b.0)
+}
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use super::super::physics_config::BodyConfig;
+
+    fn rolling(launch_angle: f32) -> Throw{
+        Throw{launch_angle, axis_rotation: 0.0, axis_tilt: 0.0, ..Throw::default()}
+    }
+
+    #[test]
+    fn straight_axis_rolls_forward(){
+        for launch_angle in [0.0, -3.0, 5.0]{
+            let throw = rolling(launch_angle);
+            let roll = throw.velocity().cross(&-Vector3::y());
+            let angvel = throw.angular_velocity();
+            assert!(angvel.normalize().dot(&roll.normalize()) > 0.9999, "{angvel:?} against {roll:?}");
+            assert!((angvel.norm() - throw.rev_rate * RPM_TO_RAD_PER_SEC).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn rolling_ball_keeps_its_revs(){
+        let mut physics = Physics::new(LaneSpec::REGULATION);
+        physics.build_colliders();
+        physics.throw_ball(&rolling(0.0));
+        let ball = physics.handle(Role::Ball).unwrap();
+        for _ in 0..60{
+            physics.simulate();
+        }
+        //a forward roll only loses a little to friction, backspin would have been turned around
+        let angvel = physics.bodies[ball].angvel();
+        assert!(angvel.x > 0.9 * rolling(0.0).rev_rate * RPM_TO_RAD_PER_SEC, "{angvel:?}");
+    }
+
+    #[test]
+    fn turned_axis_hooks_left(){
+        let hooked = |axis_rotation: f32| {
+            let mut physics = Physics::new(LaneSpec::REGULATION);
+            physics.build_colliders();
+            physics.throw_ball(&Throw{axis_rotation, axis_tilt: 0.0, ..Throw::default()});
+            let ball = physics.handle(Role::Ball).unwrap();
+            for _ in 0..60{
+                physics.simulate();
             }
-        for (i,obj) in self.physics_obj.iter().enumerate(){
-            let name = &obj.name;
-            println!("Index: {i}, Name: {name}");
-        }
-        let floor_body = RigidBodyBuilder::fixed().translation(vector![0.0,-1.0,0.0]).build();
-        let floor_handle = self.bodies.insert(floor_body);
-        let floor_collider = ColliderBuilder::cuboid(100.0, 1.0, 100.0)
-        .friction(0.1)
-        .restitution(0.0)
-        .build();
-        self.colliders.insert_with_parent(floor_collider, floor_handle, &mut self.bodies);
-        self.physics_obj.push(PhysicsObj{name: format!("Floor"),handle: floor_handle});
+            physics.bodies[ball].translation().x
+        };
+        assert!(hooked(45.0) > hooked(0.0) + 0.01);
+        assert!(hooked(-45.0) < hooked(0.0) - 0.01);
+    }
+
+    #[test]
+    fn teleports_do_not_slide_in_between_frames(){
+        let mut physics = Physics::new(LaneSpec::REGULATION);
+        physics.build_colliders();
+        physics.throw_ball(&rolling(0.0));
+        for _ in 0..60{
+            physics.simulate();
+        }
+        let ball = physics.entity(Role::Ball).unwrap();
+        let pin = physics.entity(Role::Pin(1)).unwrap();
+        physics.return_ball();
+        physics.remove_pin(1);
+        //halfway to the last step is still the new pose, not somewhere on the way from the old one
+        for (entity, handle) in [(ball, physics.handle(Role::Ball).unwrap()), (pin, physics.pin_handle(1).unwrap())]{
+            let now = physics.bodies[handle].translation();
+            let drawn = physics.get_translation(entity, 0.5).unwrap();
+            assert!((Vector3::from(drawn) - now).norm() < 1e-5, "{drawn:?} against {now:?}");
+        }
+        physics.place_pin(1, physics.pin_spot(1).unwrap());
+        let now = physics.bodies[physics.pin_handle(1).unwrap()].translation();
+        assert_eq!(physics.get_translation(pin, 0.0).unwrap(), [now.x, now.y, now.z]);
+    }
+
+    #[test]
+    fn spawned_entities_live_side_by_side(){
+        let mut physics = Physics::new(LaneSpec::REGULATION);
+        physics.build_colliders();
+        let first = physics.entity(Role::Ball).unwrap();
+        let second = physics.spawn(Role::Ball).unwrap();
+        let balls = |physics: &Physics| physics.entities()
+            .filter(|&(_, role)| role == Role::Ball)
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        assert_eq!(balls(&physics), [first, second]);
+        assert_eq!(physics.entity(Role::Ball), Some(first));
+        assert_eq!(physics.despawn(first), Some(Role::Ball));
+        assert_eq!(physics.despawn(first), None);
+        assert_eq!(balls(&physics), [second]);
+        assert_eq!(physics.role_of(physics.handle(Role::Ball).unwrap()), Some(Role::Ball));
+        //there is one pin of every number, another one only once it is gone
+        let pin = physics.entity(Role::Pin(1)).unwrap();
+        assert_eq!(physics.spawn(Role::Pin(1)), None);
+        physics.despawn(pin);
+        assert!(!physics.all_pins().contains(1));
+        let pin = physics.spawn(Role::Pin(1)).unwrap();
+        assert_eq!(physics.entity(Role::Pin(1)), Some(pin));
+        assert!(physics.pin_translation(1).is_some());
+        assert_eq!(physics.spawn(Role::Pin(11)), None);
+        //building again starts over with a single ball
+        physics.build_colliders();
+        assert_eq!(physics.entities().filter(|&(_, role)| role == Role::Ball).count(), 1);
+        assert_eq!(physics.entities().count(), 1 + 10 + 2);
+    }
+
+    #[test]
+    fn new_sizes_rebuild_the_bodies(){
+        let mut physics = Physics::new(LaneSpec::REGULATION);
+        physics.build_colliders();
+        let ball = physics.entity(Role::Ball).unwrap();
+        let head_pin = physics.pin_spot(1).unwrap();
+        let seven_pin = physics.pin_spot(7).unwrap();
+        physics.set_config(PhysicsConfig{pin_spacing: Some(0.6096), ..PhysicsConfig::default()});
+        assert_ne!(physics.entity(Role::Ball), Some(ball));
+        assert_eq!(physics.entities().count(), 1 + 10 + 2);
+        assert_eq!(physics.pin_spot(1), Some(head_pin));
+        assert_eq!(physics.standing_pins(), physics.all_pins());
+        let spread = physics.pin_spot(7).unwrap() - head_pin;
+        assert!((spread - 2.0 * (seven_pin - head_pin)).norm() < 1e-5, "{spread:?}");
+        //the same sizes again leave the bodies be
+        let ball = physics.entity(Role::Ball);
+        physics.set_config(PhysicsConfig{pin_spacing: Some(0.6096), gravity: 5.0, ..PhysicsConfig::default()});
+        assert_eq!(physics.entity(Role::Ball), ball);
+    }
 
+    #[test]
+    fn new_sizes_keep_the_pins_that_stand(){
+        let mut physics = Physics::new(LaneSpec::REGULATION);
+        physics.build_colliders();
+        let mut leave = PinSet::default();
+        leave.insert(7);
+        leave.insert(10);
+        physics.set_up_leave(leave);
+        physics.set_config(PhysicsConfig{pin: BodyConfig{radius: Some(0.05), ..PhysicsConfig::default().pin}, ..PhysicsConfig::default()});
+        assert_eq!(physics.variant().pin.radius, 0.05);
+        assert_eq!(physics.standing_pins(), leave);
+        assert_eq!(physics.pins_on_deck().len(), 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn ball_hitting_a_pin_is_reported(){
+        let mut physics = Physics::new(LaneSpec::REGULATION);
+        physics.build_colliders();
+        for _ in 0..180{
+            physics.simulate();
+        }
+        //straight at the head pin
+        physics.throw_ball(&Throw{position: 0.0, speed: 8.0, launch_angle: 0.0, rev_rate: 0.0, axis_rotation: 0.0, axis_tilt: 0.0});
+        let mut impacts = Vec::new();
+        for _ in 0..60 * 4{
+            physics.simulate();
+            impacts.extend_from_slice(physics.impacts());
+        }
+        let first = impacts.iter().find(|impact| matches!(impact.kind, ImpactKind::BallPin(_))).expect("the ball never hit a pin");
+        assert_eq!(first.kind, ImpactKind::BallPin(1));
+        assert!(first.impulse > 0.0, "{first:?}");
+        //the contact is on the head pin, between it and the ball
+        let head_pin = physics.pin_spot(1).unwrap();
+        let reach = physics.variant().pin.radius + 0.02;
+        assert!((first.position - head_pin).xz().norm() < reach, "{first:?} against {head_pin:?}");
+        assert!(impacts.iter().any(|impact| matches!(impact.kind, ImpactKind::PinPin(..))));
+        assert!(impacts.iter().any(|impact| impact.kind == ImpactKind::BallPit));
+    }
+}
@@ -0,0 +1,376 @@
+use std::fmt;
+use std::ops::Range;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use super::lane::Surface;
+use super::pin_shape;
+use super::variant::Variant;
+
+const LB_TO_KG: f32 = 0.4536;
+// the spacing every rack of the variants is laid out with
+const RACK_SPACING: f32 = 12.0 * 0.0254;
+
+// The numbers physics is tuned with, so pin carry can be changed without a rebuild. A TOML file
+// gives any of them and leaves the rest at their defaults:
+//
+//   gravity = 9.81
+//   solver_iterations = 4
+//
+//   [pin]
+//   restitution = 0.9
+//   center_of_mass = 0.387
+//   spacing = 0.3048
+//
+//   [gutter]
+//   friction = 0.3
+//
+// [ball] and [pin] take friction, restitution, linear_damping, angular_damping, radius and density,
+// the pin also center_of_mass and spacing. The surfaces ([approach], [lane], [gutter], [pit],
+// [kickback], [cushion]) take friction and restitution, except the lane bed whose friction comes
+// from the oil pattern. Radii, densities and the spacing left out are the variant's own.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    // not TOML, or a key or a type of value the config doesn't have
+    Syntax(String),
+    OutOfRange { line: usize, key: String, value: f32, range: &'static str },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Syntax(message) => write!(f, "{}", message.trim_end()),
+            ConfigError::OutOfRange { line, key, value, range } => {
+                write!(f, "line {line}: '{key}' is {value}, it has to be {range}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    pub friction: f32,
+    pub restitution: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyConfig {
+    pub material: Material,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    // m, at the pin's belly; None keeps the variant's
+    pub radius: Option<f32>,
+    // kg/m^3; None keeps the variant's pins and the ball the bowler picked
+    pub density: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicsConfig {
+    // m/s^2, pulling down
+    pub gravity: f32,
+    pub solver_iterations: usize,
+    pub ball: BodyConfig,
+    pub pin: BodyConfig,
+    // height of the pin's centre of mass over the base, as a part of the pin's height
+    pub pin_center_of_mass: f32,
+    // m between the centres of neighbouring pins; None keeps the variant's
+    pub pin_spacing: Option<f32>,
+    pub approach: Material,
+    pub lane: Material,
+    pub gutter: Material,
+    pub pit: Material,
+    pub kickback: Material,
+    pub cushion: Material,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            gravity: 9.81,
+            solver_iterations: 4,
+            ball: BodyConfig {
+                material: Material { friction: 0.5, restitution: 0.1 },
+                linear_damping: 0.01,
+                angular_damping: 0.01,
+                radius: None,
+                density: None,
+            },
+            pin: BodyConfig {
+                material: Material { friction: 0.4, restitution: 0.9 },
+                linear_damping: 1.0,
+                angular_damping: 1.0,
+                radius: None,
+                density: None,
+            },
+            // a regulation pin has its centre of gravity 5 43/64 to 5 15/16 inches above the base of its 15 inches
+            pin_center_of_mass: 5.8 / 15.0,
+            pin_spacing: None,
+            approach: Material { friction: 0.1, restitution: 0.0 },
+            lane: Material { friction: 0.1, restitution: 0.0 },
+            gutter: Material { friction: 0.3, restitution: 0.0 },
+            pit: Material { friction: 0.6, restitution: 0.0 },
+            kickback: Material { friction: 0.2, restitution: 0.3 },
+            cushion: Material { friction: 0.8, restitution: 0.1 },
+        }
+    }
+}
+
+// The file as it is written, every key may be left out. Values keep where they stand in the text
+// so a number out of range is reported with its line
+type Number = Option<Spanned<f32>>;
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    gravity: Number,
+    solver_iterations: Option<Spanned<u32>>,
+    ball: BodyFile,
+    pin: PinFile,
+    approach: MaterialFile,
+    lane: LaneFile,
+    gutter: MaterialFile,
+    pit: MaterialFile,
+    kickback: MaterialFile,
+    cushion: MaterialFile,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct BodyFile {
+    friction: Number,
+    restitution: Number,
+    linear_damping: Number,
+    angular_damping: Number,
+    radius: Number,
+    density: Number,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct PinFile {
+    friction: Number,
+    restitution: Number,
+    linear_damping: Number,
+    angular_damping: Number,
+    radius: Number,
+    density: Number,
+    center_of_mass: Number,
+    spacing: Number,
+}
+
+impl PinFile {
+    // the keys the pin shares with the ball
+    fn body(&self) -> BodyFile {
+        BodyFile {
+            friction: self.friction.clone(),
+            restitution: self.restitution.clone(),
+            linear_damping: self.linear_damping.clone(),
+            angular_damping: self.angular_damping.clone(),
+            radius: self.radius.clone(),
+            density: self.density.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct MaterialFile {
+    friction: Number,
+    restitution: Number,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct LaneFile {
+    restitution: Number,
+}
+
+// What a number may be, the text goes into the error
+type Allowed = (&'static str, fn(f32) -> bool);
+
+const POSITIVE: Allowed = ("above 0", |value| value > 0.0);
+const NOT_NEGATIVE: Allowed = ("0 or more", |value| value >= 0.0);
+const FRACTION: Allowed = ("between 0 and 1", |value| (0.0..=1.0).contains(&value));
+const INSIDE: Allowed = ("between 0 and 1", |value| value > 0.0 && value < 1.0);
+
+impl PhysicsConfig {
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let file: ConfigFile = toml::from_str(text).map_err(|e| ConfigError::Syntax(e.to_string()))?;
+        let line = |span: Range<usize>| text[..span.start].lines().count();
+        let check = |key: &str, value: &Number, (range, allowed): Allowed| match value {
+            Some(value) if !value.get_ref().is_finite() || !allowed(*value.get_ref()) => Err(ConfigError::OutOfRange {
+                line: line(value.span()),
+                key: key.to_string(),
+                value: *value.get_ref(),
+                range,
+            }),
+            value => Ok(value.as_ref().map(|value| *value.get_ref())),
+        };
+        let material = |name: &str, file: &MaterialFile, material: &mut Material| -> Result<(), ConfigError> {
+            material.friction =
+                check(&format!("{name}.friction"), &file.friction, NOT_NEGATIVE)?.unwrap_or(material.friction);
+            material.restitution =
+                check(&format!("{name}.restitution"), &file.restitution, FRACTION)?.unwrap_or(material.restitution);
+            Ok(())
+        };
+        let body = |name: &str, file: &BodyFile, body: &mut BodyConfig| {
+            let surface = MaterialFile { friction: file.friction.clone(), restitution: file.restitution.clone() };
+            material(name, &surface, &mut body.material)?;
+            body.linear_damping = check(&format!("{name}.linear_damping"), &file.linear_damping, NOT_NEGATIVE)?
+                .unwrap_or(body.linear_damping);
+            body.angular_damping = check(&format!("{name}.angular_damping"), &file.angular_damping, NOT_NEGATIVE)?
+                .unwrap_or(body.angular_damping);
+            body.radius = check(&format!("{name}.radius"), &file.radius, POSITIVE)?.or(body.radius);
+            body.density = check(&format!("{name}.density"), &file.density, POSITIVE)?.or(body.density);
+            Ok(())
+        };
+
+        let mut config = Self::default();
+        config.gravity = check("gravity", &file.gravity, POSITIVE)?.unwrap_or(config.gravity);
+        if let Some(iterations) = file.solver_iterations {
+            if !(1..=64).contains(iterations.get_ref()) {
+                return Err(ConfigError::OutOfRange {
+                    line: line(iterations.span()),
+                    key: "solver_iterations".to_string(),
+                    value: *iterations.get_ref() as f32,
+                    range: "a whole number from 1 to 64",
+                });
+            }
+            config.solver_iterations = *iterations.get_ref() as usize;
+        }
+        body("ball", &file.ball, &mut config.ball)?;
+        body("pin", &file.pin.body(), &mut config.pin)?;
+        config.pin_center_of_mass =
+            check("pin.center_of_mass", &file.pin.center_of_mass, INSIDE)?.unwrap_or(config.pin_center_of_mass);
+        config.pin_spacing = check("pin.spacing", &file.pin.spacing, POSITIVE)?.or(config.pin_spacing);
+        material("approach", &file.approach, &mut config.approach)?;
+        config.lane.restitution =
+            check("lane.restitution", &file.lane.restitution, FRACTION)?.unwrap_or(config.lane.restitution);
+        material("gutter", &file.gutter, &mut config.gutter)?;
+        material("pit", &file.pit, &mut config.pit)?;
+        material("kickback", &file.kickback, &mut config.kickback)?;
+        material("cushion", &file.cushion, &mut config.cushion)?;
+        Ok(config)
+    }
+
+    pub fn surface(&self, surface: Surface) -> Material {
+        match surface {
+            Surface::Approach => self.approach,
+            Surface::Lane => self.lane,
+            Surface::Gutter => self.gutter,
+            Surface::Pit => self.pit,
+            Surface::Kickback => self.kickback,
+            Surface::Cushion => self.cushion,
+        }
+    }
+
+    // The variant with the radii, densities and spacing of the config in place of its own. A ball
+    // density gives the ball a weight of its own, the one the bowler picked doesn't count then
+    pub fn sized(&self, variant: &Variant) -> Variant {
+        let mut sized = variant.clone();
+        if let Some(radius) = self.ball.radius {
+            sized.ball.radius = radius;
+        }
+        if let Some(density) = self.ball.density {
+            let volume = 4.0 / 3.0 * std::f32::consts::PI * sized.ball.radius.powi(3);
+            sized.ball.weight_lb = Some(density * volume / LB_TO_KG);
+        }
+        if let Some(radius) = self.pin.radius {
+            sized.pin.radius = radius;
+        }
+        if let Some(density) = self.pin.density {
+            sized.pin.mass = density * pin_shape::pin_volume(&sized.pin);
+        }
+        if let Some(spacing) = self.pin_spacing {
+            let scale = spacing / RACK_SPACING;
+            for spot in &mut sized.rack {
+                spot.x *= scale;
+                spot.depth *= scale;
+            }
+        }
+        sized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_file_is_the_default() {
+        assert_eq!(PhysicsConfig::parse(include_str!("../res/physics.toml")), Ok(PhysicsConfig::default()));
+        assert_eq!(PhysicsConfig::parse(""), Ok(PhysicsConfig::default()));
+    }
+
+    #[test]
+    fn keys_left_out_keep_their_defaults() {
+        let config =
+            PhysicsConfig::parse("gravity = 5\n[pin]\nrestitution = 0.5\nspacing = 0.3\n[gutter]\nfriction = 1")
+                .unwrap();
+        let default = PhysicsConfig::default();
+        assert_eq!(config.gravity, 5.0);
+        assert_eq!(config.pin.material, Material { restitution: 0.5, ..default.pin.material });
+        assert_eq!(config.pin_spacing, Some(0.3));
+        assert_eq!(config.gutter.friction, 1.0);
+        assert_eq!(config.ball, default.ball);
+        assert_eq!(config.pit, default.pit);
+    }
+
+    #[test]
+    fn out_of_range_is_told_with_its_line() {
+        let error = PhysicsConfig::parse("gravity = 9.81\n\n[pin]\nrestitution = 1.5\n").unwrap_err();
+        assert_eq!(
+            error,
+            ConfigError::OutOfRange {
+                line: 4,
+                key: "pin.restitution".to_string(),
+                value: 1.5,
+                range: "between 0 and 1"
+            }
+        );
+        assert!(matches!(PhysicsConfig::parse("solver_iterations = 0"), Err(ConfigError::OutOfRange { line: 1, .. })));
+        assert!(matches!(PhysicsConfig::parse("[ball]\nradius = -1"), Err(ConfigError::OutOfRange { line: 2, .. })));
+        assert!(matches!(PhysicsConfig::parse("gravity = inf"), Err(ConfigError::OutOfRange { .. })));
+    }
+
+    #[test]
+    fn unknown_keys_and_bad_values_are_refused() {
+        for text in
+            ["gravty = 9.81", "[lane]\nfriction = 0.1", "[ball]\nspacing = 0.3", "gravity = \"down\"", "gravity 9.81"]
+        {
+            let error = PhysicsConfig::parse(text).unwrap_err();
+            assert!(matches!(error, ConfigError::Syntax(_)), "{text}: {error:?}");
+            assert!(error.to_string().contains("line"), "{text}: {error}");
+        }
+    }
+
+    #[test]
+    fn sizes_replace_the_variant_ones() {
+        let variant = Variant::default();
+        assert_eq!(PhysicsConfig::default().sized(&variant), variant);
+        let config =
+            PhysicsConfig::parse("[ball]\nradius = 0.1\ndensity = 1000\n[pin]\nradius = 0.05\nspacing = 0.6096")
+                .unwrap();
+        let sized = config.sized(&variant);
+        assert_eq!(sized.ball.radius, 0.1);
+        let ball_kg = 1000.0 * 4.0 / 3.0 * std::f32::consts::PI * 0.001;
+        assert!((sized.ball.weight_lb.unwrap() * LB_TO_KG - ball_kg).abs() < 1e-3);
+        assert_eq!(sized.pin.radius, 0.05);
+        assert_eq!(sized.pin.mass, variant.pin.mass);
+        // twice the spacing, the rack spreads out twice as far from the head pin
+        for (sized, spot) in sized.rack.iter().zip(&variant.rack) {
+            assert!((sized.x - 2.0 * spot.x).abs() < 1e-6 && (sized.depth - 2.0 * spot.depth).abs() < 1e-6);
+        }
+        // the regulation pin weighs the same at the density it has
+        let density = variant.pin.mass / pin_shape::pin_volume(&variant.pin);
+        let same = PhysicsConfig {
+            pin: BodyConfig { density: Some(density), ..PhysicsConfig::default().pin },
+            ..PhysicsConfig::default()
+        };
+        assert!((same.sized(&variant).pin.mass - variant.pin.mass).abs() < 1e-4, "{density}");
+    }
+}
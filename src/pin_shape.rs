@@ -0,0 +1,177 @@
+use rapier3d::prelude::*;
+
+use super::variant::PinSpec;
+
+// The pin that is drawn, its collider is built from the same mesh
+const PIN_OBJ: &str = include_str!("../res/pin.obj");
+// Rings of the profile closer together than this share a piece, the crown of the mesh is finely cut
+const MIN_PIECE: f32 = 1.0 / 40.0;
+const SEGMENTS: usize = 12;
+// Slices of every piece when the pin is weighed
+const DISCS: usize = 16;
+// Flat faces give degenerate contacts with the floor and tip the pins over on their own, so every
+// piece is rounded off by this much of the pin's radius
+const ROUNDING: f32 = 0.15;
+
+// The pin's outline, the mesh is turned on a lathe around the y axis
+struct Profile {
+    // (height, radius) from the base up
+    rings: Vec<(f32, f32)>,
+    height: f32,
+    radius: f32,
+}
+
+impl Profile {
+    fn from_obj(obj: &str) -> Self {
+        let vertices = obj.lines().filter_map(|line| line.strip_prefix("v ")).filter_map(|line| {
+            let numbers = line.split_whitespace().map(|field| field.parse::<f32>().ok()).collect::<Option<Vec<f32>>>()?;
+            let [x, y, z] = numbers[..] else { return None };
+            Some((y, x.hypot(z)))
+        });
+        // the widest vertex of every ring, rings are told apart by their height
+        let mut rings: Vec<(f32, f32)> = Vec::new();
+        for (y, r) in vertices {
+            match rings.iter_mut().find(|(height, _)| (height - y).abs() < 1e-4) {
+                Some(ring) => ring.1 = ring.1.max(r),
+                None => rings.push((y, r)),
+            }
+        }
+        rings.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let base = rings.first().map_or(0.0, |ring| ring.0);
+        let top = rings.last().map_or(0.0, |ring| ring.0);
+        let height = top - base;
+        let radius = rings.iter().fold(0.0f32, |radius, ring| radius.max(ring.1));
+        let mut kept: Vec<(f32, f32)> = Vec::new();
+        for (i, &(y, r)) in rings.iter().enumerate() {
+            let last = i + 1 == rings.len();
+            if kept.last().is_none_or(|ring| last || y - ring.0 >= MIN_PIECE * height) {
+                kept.push((y - base, r));
+            }
+        }
+        Self { rings: kept, height, radius }
+    }
+}
+
+// The pin of the variant as a compound of convex pieces, one between every two rings of the profile,
+// centred on the pin's half height like its body. center_of_mass is its height over the base, as a
+// part of the pin's height
+pub fn pin_collider(pin: &PinSpec, center_of_mass: f32) -> ColliderBuilder {
+    let profile = Profile::from_obj(PIN_OBJ);
+    let scale_y = 2.0 * pin.half_height / profile.height;
+    let scale_r = pin.radius / profile.radius;
+    let border = ROUNDING * pin.radius;
+    let ring = |(y, r): (f32, f32)| {
+        let (y, r) = (y * scale_y - pin.half_height, (r * scale_r - border).max(0.0));
+        (0..SEGMENTS).map(move |i| {
+            let angle = i as f32 * std::f32::consts::TAU / SEGMENTS as f32;
+            point![r * angle.cos(), y.clamp(border - pin.half_height, pin.half_height - border), r * angle.sin()]
+        })
+    };
+    let pieces = profile
+        .rings
+        .windows(2)
+        .filter_map(|pair| {
+            let points = ring(pair[0]).chain(ring(pair[1])).collect::<Vec<_>>();
+            SharedShape::round_convex_hull(&points, border).map(|shape| (Isometry::identity(), shape))
+        })
+        .collect::<Vec<_>>();
+    let shape = SharedShape::compound(pieces);
+    ColliderBuilder::new(shape).mass_properties(mass_properties(pin, &profile, center_of_mass))
+}
+
+// Mass and inertia of the pin as a solid of revolution cut into thin discs, with its moments taken
+// around the given centre of mass
+pub fn pin_mass_properties(pin: &PinSpec, center_of_mass: f32) -> MassProperties {
+    mass_properties(pin, &Profile::from_obj(PIN_OBJ), center_of_mass)
+}
+
+// m^3 of the pin, taken in the same discs it is weighed in
+pub fn pin_volume(pin: &PinSpec) -> f32 {
+    discs(pin, &Profile::from_obj(PIN_OBJ), 0.5).0
+}
+
+fn mass_properties(pin: &PinSpec, profile: &Profile, center_of_mass: f32) -> MassProperties {
+    let center = (center_of_mass - 0.5) * 2.0 * pin.half_height;
+    let (volume, axial, across) = discs(pin, profile, center_of_mass);
+    let density = pin.mass / volume;
+    let inertia = vector![across * density, axial * density, across * density];
+    MassProperties::new(point![0.0, center, 0.0], pin.mass, inertia)
+}
+
+// Volume of the pin with its moments per unit of density, around its axis and across it through
+// the given centre of mass
+fn discs(pin: &PinSpec, profile: &Profile, center_of_mass: f32) -> (f32, f32, f32) {
+    let scale_y = 2.0 * pin.half_height / profile.height;
+    let scale_r = pin.radius / profile.radius;
+    let center = (center_of_mass - 0.5) * 2.0 * pin.half_height;
+    let (mut volume, mut axial, mut across) = (0.0, 0.0, 0.0);
+    for pair in profile.rings.windows(2) {
+        let ((y0, r0), (y1, r1)) = ((pair[0].0 * scale_y, pair[0].1 * scale_r), (pair[1].0 * scale_y, pair[1].1 * scale_r));
+        let dy = (y1 - y0) / DISCS as f32;
+        for i in 0..DISCS {
+            let t = (i as f32 + 0.5) / DISCS as f32;
+            let (y, r) = (y0 + (y1 - y0) * t - pin.half_height - center, r0 + (r1 - r0) * t);
+            let disc = std::f32::consts::PI * r * r * dy;
+            volume += disc;
+            axial += disc * r * r / 2.0;
+            across += disc * (r * r / 4.0 + y * y);
+        }
+    }
+    (volume, axial, across)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::physics_config::PhysicsConfig;
+    use super::super::variant::{Variant, VariantKind};
+    use super::*;
+
+    const INCH: f32 = 0.0254;
+
+    #[test]
+    fn regulation_pin_weighs_what_the_spec_says() {
+        let pin = Variant::new(VariantKind::TenPin).pin;
+        let center_of_mass = PhysicsConfig::default().pin_center_of_mass;
+        let mass = pin_mass_properties(&pin, center_of_mass);
+        // 3 lb 6 oz, the lightest a pin may be
+        assert_eq!(mass.mass(), 1.53);
+        // 5.8 in over the base of a 15 in pin, the body is centred on its half height
+        let height = mass.local_com.y + pin.half_height;
+        assert!((height - 5.8 * INCH).abs() < 1e-5, "{}", height / INCH);
+        assert_eq!((mass.local_com.x, mass.local_com.z), (0.0, 0.0));
+        // maple under a coat of plastic
+        let density = pin.mass / pin_volume(&pin);
+        assert!((700.0..900.0).contains(&density), "{density}");
+        // tall and slim: much easier to spin around the axis than to tip over
+        let inertia = mass.principal_inertia();
+        assert_eq!(inertia.x, inertia.z);
+        assert!(inertia.y < inertia.x / 5.0, "{inertia:?}");
+    }
+
+    #[test]
+    fn center_of_mass_goes_where_the_config_puts_it() {
+        let pin = Variant::new(VariantKind::TenPin).pin;
+        let middle = pin_mass_properties(&pin, 0.5);
+        let high = pin_mass_properties(&pin, 0.9);
+        assert_eq!(middle.local_com.y, 0.0);
+        assert!((high.local_com.y - 0.4 * 2.0 * pin.half_height).abs() < 1e-6);
+        // the moment around the axis is the same wherever the weight sits, across it grows away from the belly
+        assert_eq!(middle.principal_inertia().y, high.principal_inertia().y);
+        assert!(high.principal_inertia().x > middle.principal_inertia().x);
+    }
+
+    #[test]
+    fn collider_follows_the_mesh() {
+        for kind in [VariantKind::TenPin, VariantKind::Duckpin, VariantKind::FivePin] {
+            let pin = Variant::new(kind).pin;
+            let collider = pin_collider(&pin, 0.4).build();
+            // a rounded hull between every two rings of the profile, none of them left out
+            let rings = Profile::from_obj(PIN_OBJ).rings.len();
+            assert_eq!(collider.shape().as_compound().unwrap().shapes().len(), rings - 1, "{kind:?}");
+            let aabb = collider.shape().compute_local_aabb();
+            assert!((aabb.maxs.y - pin.half_height).abs() < 1e-5 && (aabb.mins.y + pin.half_height).abs() < 1e-5);
+            assert!((aabb.maxs.x - pin.radius).abs() < 0.01 * pin.radius, "{kind:?} {}", aabb.maxs.x);
+            assert_eq!(collider.mass(), pin.mass);
+        }
+    }
+}
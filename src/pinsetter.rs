@@ -0,0 +1,193 @@
+use nalgebra::Vector3;
+
+use super::phys::{Physics, PinSet};
+
+//how long each step of the machine takes, in simulated seconds
+const LIFT_TIME: f32 = 1.0;
+const SWEEP_TIME: f32 = 1.5;
+const SET_TIME: f32 = 1.0;
+//how high the standing pins are held while the deck is swept
+const LIFT_HEIGHT: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinsetterPhase {
+    Idle,
+    // standing pins are picked up off the deck
+    Lifting,
+    // the sweep clears the deadwood into the pit
+    Sweeping,
+    // lifted pins are respotted, or a fresh rack is set
+    Setting,
+}
+
+// Automatic pinsetter. After the first ball of a frame it clears the fallen pins and
+// respots the standing ones where they stood, after the frame is over it sets a new rack.
+pub struct Pinsetter {
+    phase: PinsetterPhase,
+    // candlepin leaves the deadwood where it fell until the frame is over
+    sweep_deadwood: bool,
+    timer: f32,
+    rerack: bool,
+    lifted: Vec<(u8, Vector3<f32>)>,
+}
+
+impl Pinsetter {
+    pub fn new(sweep_deadwood: bool) -> Self {
+        Self {
+            phase: PinsetterPhase::Idle,
+            sweep_deadwood,
+            timer: 0.0,
+            rerack: false,
+            lifted: Vec::new(),
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.phase != PinsetterPhase::Idle
+    }
+
+    pub fn start_cycle(&mut self, physics: &mut Physics, rerack: bool) {
+        if !rerack && !self.sweep_deadwood {
+            //only the ball comes back
+            physics.return_ball();
+            return;
+        }
+        let standing = physics
+            .standing_pins()
+            .iter()
+            .filter_map(|pin| physics.pin_translation(pin).map(|position| (pin, position)))
+            .collect();
+        self.begin(physics, rerack, standing);
+    }
+
+    // After a foul or a gutter ball the pins count as untouched: the deck is swept
+    // and the given pins go back on their spots
+    pub fn respot(&mut self, physics: &mut Physics, pins: PinSet, rerack: bool) {
+        let spots = pins.iter().filter_map(|pin| physics.pin_spot(pin).map(|spot| (pin, spot))).collect();
+        self.begin(physics, rerack, spots);
+    }
+
+    fn begin(&mut self, physics: &mut Physics, rerack: bool, keep: Vec<(u8, Vector3<f32>)>) {
+        physics.return_ball();
+        self.rerack = rerack;
+        self.lifted.clear();
+        if !rerack {
+            for (pin, position) in keep {
+                physics.lift_pin(pin, LIFT_HEIGHT);
+                self.lifted.push((pin, position));
+            }
+        }
+        self.enter(PinsetterPhase::Lifting);
+    }
+
+    fn enter(&mut self, phase: PinsetterPhase) {
+        self.phase = phase;
+        self.timer = 0.0;
+    }
+
+    pub fn update(&mut self, physics: &mut Physics, dt: f32) {
+        self.timer += dt;
+        match self.phase {
+            PinsetterPhase::Lifting if self.timer >= LIFT_TIME => {
+                let mut deadwood = physics.all_pins();
+                for (pin, _) in &self.lifted {
+                    deadwood.remove(*pin);
+                }
+                for pin in deadwood.iter() {
+                    physics.remove_pin(pin);
+                }
+                self.enter(PinsetterPhase::Sweeping);
+            }
+            PinsetterPhase::Sweeping if self.timer >= SWEEP_TIME => {
+                if self.rerack {
+                    physics.rerack();
+                } else {
+                    for (pin, position) in &self.lifted {
+                        physics.place_pin(*pin, *position);
+                    }
+                }
+                self.enter(PinsetterPhase::Setting);
+            }
+            PinsetterPhase::Setting if self.timer >= SET_TIME => {
+                self.enter(PinsetterPhase::Idle);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Default for Pinsetter {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Isometry3, UnitQuaternion};
+
+    use super::super::lane::LaneSpec;
+    use super::*;
+
+    // Runs the machine until it is done, the physics stepping along with it
+    fn finish(pinsetter: &mut Pinsetter, physics: &mut Physics) {
+        for _ in 0..60 * 10 {
+            physics.simulate();
+            pinsetter.update(physics, physics.timestep());
+            if !pinsetter.is_busy() {
+                return;
+            }
+        }
+        panic!("the pinsetter never finished");
+    }
+
+    #[test]
+    fn standing_pins_are_respotted_where_they_stood() {
+        let mut physics = Physics::new(LaneSpec::REGULATION);
+        physics.build_colliders();
+        let half_height = physics.variant().pin.half_height;
+        let radius = physics.variant().pin.radius;
+        // the head pin lies on the deck, the 5 was knocked off its spot but still stands
+        let moved = physics.pin_spot(5).unwrap() + Vector3::new(0.03, 0.0, 0.02);
+        let deck: Vec<(u8, Isometry3<f32>)> = physics
+            .all_pins()
+            .iter()
+            .map(|pin| {
+                let spot = physics.pin_spot(pin).unwrap();
+                let position = match pin {
+                    1 => Isometry3::from_parts(
+                        Vector3::new(spot.x, radius, spot.z).into(),
+                        UnitQuaternion::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2),
+                    ),
+                    5 => Isometry3::translation(moved.x, half_height, moved.z),
+                    _ => Isometry3::translation(spot.x, half_height, spot.z),
+                };
+                (pin, position)
+            })
+            .collect();
+        physics.restore_pins(&deck);
+        let mut standing = physics.all_pins();
+        standing.remove(1);
+        assert_eq!(physics.standing_pins(), standing);
+
+        let mut pinsetter = Pinsetter::default();
+        pinsetter.start_cycle(&mut physics, false);
+        assert!(pinsetter.is_busy());
+        finish(&mut pinsetter, &mut physics);
+        // the deadwood is gone, the rest stand exactly where they did
+        let on_deck: Vec<u8> = physics.pins_on_deck().iter().map(|(pin, _)| *pin).collect();
+        assert_eq!(on_deck, standing.iter().collect::<Vec<_>>());
+        assert_eq!(physics.standing_pins(), standing);
+        let five = physics.pin_translation(5).unwrap();
+        assert!((five.x - moved.x).abs() < 1e-4 && (five.z - moved.z).abs() < 1e-4, "{five:?}");
+        let ten = physics.pin_translation(10).unwrap();
+        assert!((ten - physics.pin_spot(10).unwrap()).xz().norm() < 1e-4, "{ten:?}");
+
+        // once the frame is over all the pins go back on their spots
+        pinsetter.start_cycle(&mut physics, true);
+        finish(&mut pinsetter, &mut physics);
+        assert_eq!(physics.standing_pins(), physics.all_pins());
+        let five = physics.pin_translation(5).unwrap();
+        assert!((five - physics.pin_spot(5).unwrap()).xz().norm() < 1e-4, "{five:?}");
+    }
+}
@@ -0,0 +1,421 @@
+use std::fmt;
+
+use super::bot::{Bot, Skill, MAX_AVERAGE, MIN_AVERAGE};
+use super::scoring::{Rules, ScoreSheet};
+
+pub const MAX_PLAYERS: usize = 8;
+const MIN_BALL_WEIGHT: u8 = 6;
+const MAX_BALL_WEIGHT: u8 = 16;
+const DEFAULT_BALL: BallChoice = BallChoice { weight_lb: 14 };
+// what splits a player argument up, a name with one of them couldn't be given back the same way
+const SEPARATORS: [char; 4] = ['=', ',', ':', '@'];
+
+//one ball colour per lane slot
+const PALETTE: [[f32; 3]; MAX_PLAYERS] = [
+    [0.2, 0.4, 1.0],
+    [1.0, 0.25, 0.2],
+    [0.2, 0.85, 0.3],
+    [1.0, 0.8, 0.1],
+    [0.7, 0.3, 0.9],
+    [1.0, 0.5, 0.1],
+    [0.1, 0.85, 0.85],
+    [0.95, 0.95, 0.95],
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineupError {
+    NoPlayers,
+    TooManyPlayers(usize),
+    BadBallWeight(String),
+    BadAverage(String),
+    EmptyTeam(String),
+    BadName(String),
+}
+
+impl fmt::Display for LineupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineupError::NoPlayers => write!(f, "a game needs at least one player"),
+            LineupError::TooManyPlayers(count) => {
+                write!(f, "{count} players given, at most {MAX_PLAYERS} can share a lane")
+            }
+            LineupError::BadBallWeight(weight) => write!(
+                f,
+                "ball weight '{weight}' must be between {MIN_BALL_WEIGHT} and {MAX_BALL_WEIGHT} lb"
+            ),
+            LineupError::EmptyTeam(team) => write!(f, "team '{team}' has no bowlers"),
+            LineupError::BadName(name) => {
+                write!(f, "name '{name}' can't have a '=', ',', ':', '@' or a line break in it")
+            }
+            LineupError::BadAverage(average) => {
+                write!(f, "bot average '{average}' must be between {MIN_AVERAGE} and {MAX_AVERAGE}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LineupError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BallChoice {
+    pub weight_lb: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct Player {
+    pub name: String,
+    pub ball: BallChoice,
+    pub color: [f32; 3],
+    pub score: ScoreSheet,
+    // None for a human at the keyboard
+    pub bot: Option<Bot>,
+    // pins added to every game, only in league play
+    pub handicap: Option<u16>,
+    // Baker team: the members take turns frame by frame on the team's score sheet
+    pub members: Vec<Player>,
+}
+
+impl Player {
+    pub fn new(name: &str, ball: BallChoice, color: [f32; 3]) -> Self {
+        Self { name: name.to_string(), ball, color, score: ScoreSheet::new(), bot: None, handicap: None, members: Vec::new() }
+    }
+
+    // Who throws the given frame, a bowler on their own throws them all
+    pub fn bowler(&self, frame: usize) -> &Player {
+        match self.members.len() {
+            0 => self,
+            count => &self.members[frame % count],
+        }
+    }
+
+    pub fn bowler_mut(&mut self, frame: usize) -> &mut Player {
+        match self.members.len() {
+            0 => self,
+            count => &mut self.members[frame % count],
+        }
+    }
+
+    pub fn total_with_handicap(&self) -> u16 {
+        self.score.total() + self.handicap.unwrap_or(0)
+    }
+
+    // "Name" or "Name:weight", the colour comes from the player's slot.
+    // "Name@average" is a computer bowler that averages about that much,
+    // "Team=Bowler,Bowler,..." a Baker team made of bowlers given the same way
+    pub fn parse(arg: &str, slot: usize) -> Result<Self, LineupError> {
+        if let Some((team, members)) = arg.split_once('=') {
+            let members = members
+                .split(',')
+                .filter(|member| !member.trim().is_empty())
+                .map(|member| Self::parse(member, slot))
+                .collect::<Result<Vec<_>, _>>()?;
+            if members.is_empty() {
+                return Err(LineupError::EmptyTeam(team.to_string()));
+            }
+            let mut player = Self::new(checked_name(team)?, DEFAULT_BALL, PALETTE[slot % MAX_PLAYERS]);
+            player.members = members;
+            return Ok(player);
+        }
+        let (name, ball) = match arg.split_once(':') {
+            Some((name, weight)) => {
+                let weight_lb = weight
+                    .trim()
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|lb| (MIN_BALL_WEIGHT..=MAX_BALL_WEIGHT).contains(lb))
+                    .ok_or_else(|| LineupError::BadBallWeight(weight.to_string()))?;
+                (name, BallChoice { weight_lb })
+            }
+            None => (arg, DEFAULT_BALL),
+        };
+        let (name, bot) = match name.split_once('@') {
+            Some((name, average)) => {
+                let average = average
+                    .trim()
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|average| (MIN_AVERAGE..=MAX_AVERAGE).contains(average))
+                    .ok_or_else(|| LineupError::BadAverage(average.to_string()))?;
+                (name, Some(Bot::new(Skill::for_average(average))))
+            }
+            None => (name, None),
+        };
+        let mut player = Self::new(checked_name(name)?, ball, PALETTE[slot % MAX_PLAYERS]);
+        player.bot = bot;
+        Ok(player)
+    }
+
+    // The argument parse makes this player from again, the names parse takes have no separators to get in the way
+    pub fn to_arg(&self) -> String {
+        if !self.members.is_empty() {
+            let members: Vec<String> = self.members.iter().map(Player::to_arg).collect();
+            return format!("{}={}", self.name, members.join(","));
+        }
+        match &self.bot {
+            Some(bot) => format!("{}@{}:{}", self.name, bot.skill.average, self.ball.weight_lb),
+            None => format!("{}:{}", self.name, self.ball.weight_lb),
+        }
+    }
+}
+
+// The name of a player argument, trimmed. Names go into saved games a line each, between the separators
+fn checked_name(name: &str) -> Result<&str, LineupError> {
+    let name = name.trim();
+    if name.contains(SEPARATORS) || name.contains(char::is_control) {
+        return Err(LineupError::BadName(name.to_string()));
+    }
+    Ok(name)
+}
+
+// The bowlers sharing a lane, in throwing order
+pub struct Lineup {
+    players: Vec<Player>,
+    active: usize,
+}
+
+impl Lineup {
+    pub fn new(players: Vec<Player>) -> Result<Self, LineupError> {
+        match players.len() {
+            0 => Err(LineupError::NoPlayers),
+            count if count > MAX_PLAYERS => Err(LineupError::TooManyPlayers(count)),
+            _ => Ok(Self { players, active: 0 }),
+        }
+    }
+
+    // A lineup part way through a game: the bowler up is the first of those a frame behind
+    pub fn resume(players: Vec<Player>) -> Result<Self, LineupError> {
+        let mut lineup = Self::new(players)?;
+        lineup.active = lineup
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| !player.score.is_complete())
+            .min_by_key(|(_, player)| player.score.current_frame())
+            .map_or(0, |(index, _)| index);
+        Ok(lineup)
+    }
+
+    // Builds the lineup from command line arguments, a single anonymous bowler if there are none
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Result<Self, LineupError> {
+        let mut players = Vec::new();
+        for (slot, arg) in args.enumerate() {
+            players.push(Player::parse(&arg, slot)?);
+        }
+        if players.is_empty() {
+            players.push(Player::new("Player 1", DEFAULT_BALL, PALETTE[0]));
+        }
+        Self::new(players)
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn players_mut(&mut self) -> &mut [Player] {
+        &mut self.players
+    }
+
+    pub fn active(&self) -> &Player {
+        &self.players[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Player {
+        &mut self.players[self.active]
+    }
+
+    // The bowler on the approach, for a Baker team the member who throws this frame
+    pub fn bowler(&self) -> &Player {
+        let player = self.active();
+        player.bowler(player.score.current_frame())
+    }
+
+    pub fn bowler_mut(&mut self) -> &mut Player {
+        let player = self.active_mut();
+        player.bowler_mut(player.score.current_frame())
+    }
+
+    // "Name", or "Team (Name)" when a team is up
+    pub fn bowler_name(&self) -> String {
+        let player = self.active();
+        if player.members.is_empty() {
+            player.name.clone()
+        } else {
+            format!("{} ({})", player.name, self.bowler().name)
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.players.iter().all(|player| player.score.is_complete())
+    }
+
+    // Hands the lane to the next bowler who still has frames to bowl
+    pub fn next_turn(&mut self) {
+        for step in 1..=self.players.len() {
+            let next = (self.active + step) % self.players.len();
+            if !self.players[next].score.is_complete() {
+                self.active = next;
+                return;
+            }
+        }
+    }
+
+    pub fn leader(&self) -> &Player {
+        self.players.iter().max_by_key(|player| player.total_with_handicap()).unwrap_or(self.active())
+    }
+
+    // Seeds every computer bowler, the team members included, each with a seed of its own
+    pub fn seed_bots(&mut self, seed: u64) {
+        let bots = self.players.iter_mut().flat_map(|player| {
+            std::iter::once(&mut player.bot).chain(player.members.iter_mut().map(|member| &mut member.bot))
+        });
+        for (index, bot) in bots.enumerate() {
+            if let Some(bot) = bot {
+                bot.seed(seed.wrapping_add(index as u64));
+            }
+        }
+    }
+
+    // Fresh score sheets for a new game
+    pub fn reset(&mut self, rules: Rules) {
+        for player in &mut self.players {
+            player.score = ScoreSheet::with_rules(rules);
+        }
+        self.active = 0;
+    }
+}
+
+impl fmt::Display for Lineup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, player) in self.players.iter().enumerate() {
+            let marker = if index == self.active { '>' } else { ' ' };
+            if player.members.is_empty() {
+                write!(f, "{marker} {} ({} lb", player.name, player.ball.weight_lb)?;
+                if let Some(bot) = &player.bot {
+                    write!(f, ", bot averaging {}", bot.skill.average)?;
+                }
+            } else {
+                let members: Vec<&str> = player.members.iter().map(|member| member.name.as_str()).collect();
+                write!(f, "{marker} {} ({}", player.name, members.join(", "))?;
+            }
+            match player.handicap {
+                Some(handicap) => writeln!(
+                    f,
+                    "), scratch {}, handicap {handicap}, total {}",
+                    player.score.total(),
+                    player.total_with_handicap()
+                )?,
+                None => writeln!(f, "), total {}", player.score.total())?,
+            }
+            writeln!(f, "{}", player.score)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lineup(args: &[&str]) -> Lineup {
+        Lineup::from_args(args.iter().map(|arg| arg.to_string())).unwrap()
+    }
+
+    // An open frame of `pins` for whoever is up
+    fn bowl_frame(lineup: &mut Lineup, pins: u8) {
+        lineup.active_mut().score.roll(pins).unwrap();
+        lineup.active_mut().score.roll(0).unwrap();
+    }
+
+    // Who throws every frame of the game, in the order they get the lane
+    fn turns(lineup: &mut Lineup) -> Vec<String> {
+        let mut turns = Vec::new();
+        while !lineup.is_complete() {
+            turns.push(lineup.bowler_name());
+            bowl_frame(lineup, 5);
+            lineup.next_turn();
+        }
+        turns
+    }
+
+    #[test]
+    fn bowlers_take_turns_frame_by_frame() {
+        for count in [1, 2, 5] {
+            let names: Vec<String> = (1..=count).map(|number| format!("P{number}")).collect();
+            let mut lineup = Lineup::from_args(names.iter().cloned()).unwrap();
+            let expected: Vec<String> = (0..10).flat_map(|_| names.iter().cloned()).collect();
+            assert_eq!(turns(&mut lineup), expected, "{count} bowlers");
+        }
+    }
+
+    #[test]
+    fn baker_team_members_take_the_frames_in_turn() {
+        let mut lineup = lineup(&["Eagles=A,B,C,D,E", "Hawks=F,G,H", "Solo"]);
+        let turns = turns(&mut lineup);
+        assert_eq!(turns.len(), 30);
+        let eagles: Vec<&str> = turns.iter().step_by(3).map(String::as_str).collect();
+        assert_eq!(eagles[..6], ["Eagles (A)", "Eagles (B)", "Eagles (C)", "Eagles (D)", "Eagles (E)", "Eagles (A)"]);
+        // five bowlers share ten frames, two each
+        assert_eq!(eagles.iter().filter(|turn| **turn == "Eagles (E)").count(), 2);
+        let hawks: Vec<&str> = turns.iter().skip(1).step_by(3).map(String::as_str).collect();
+        // frame % 3: F bowls the first, fourth, seventh and tenth
+        assert_eq!(hawks.iter().filter(|turn| **turn == "Hawks (F)").count(), 4);
+        assert_eq!(hawks[9], "Hawks (F)");
+        assert!(turns.iter().skip(2).step_by(3).all(|turn| turn == "Solo"));
+        // the members bowl on the team's sheet
+        assert_eq!(lineup.players()[0].score.total(), 50);
+        assert!(lineup.players()[0].members.iter().all(|member| member.score.frames()[0].rolls.is_empty()));
+    }
+
+    #[test]
+    fn the_tenth_frame_ends_the_turns() {
+        let mut lineup = lineup(&["Ania", "Bartek", "Celina"]);
+        // Ania's game is bowled already
+        for _ in 0..10 {
+            bowl_frame(&mut lineup, 9);
+        }
+        lineup.next_turn();
+        assert_eq!(lineup.active().name, "Bartek");
+        lineup.next_turn();
+        lineup.next_turn();
+        assert_eq!(lineup.active().name, "Bartek");
+        while !lineup.is_complete() {
+            bowl_frame(&mut lineup, 9);
+            lineup.next_turn();
+        }
+        // nobody left to hand the lane to
+        assert_eq!(lineup.active().name, "Celina");
+    }
+
+    #[test]
+    fn resume_gives_the_lane_to_whoever_is_behind() {
+        let mut lineup = lineup(&["Ania", "Bartek", "Celina"]);
+        for _ in 0..8 {
+            bowl_frame(&mut lineup, 7);
+            lineup.next_turn();
+        }
+        // two frames each and the third for Ania and Bartek, Celina is up
+        let resumed = Lineup::resume(lineup.players().to_vec()).unwrap();
+        assert_eq!(resumed.active().name, "Celina");
+        // a frame half bowled is finished first
+        lineup.active_mut().score.roll(4).unwrap();
+        lineup.next_turn();
+        lineup.active_mut().score.roll(4).unwrap();
+        let resumed = Lineup::resume(lineup.players().to_vec()).unwrap();
+        assert_eq!(resumed.active().name, "Celina");
+        // a finished game starts from the first bowler
+        let mut finished = self::lineup(&["Ania", "Bartek"]);
+        turns(&mut finished);
+        assert_eq!(Lineup::resume(finished.players().to_vec()).unwrap().active().name, "Ania");
+    }
+
+    #[test]
+    fn leader_counts_the_handicap() {
+        let mut lineup = lineup(&["Ania", "Bartek"]);
+        bowl_frame(&mut lineup, 9);
+        lineup.next_turn();
+        bowl_frame(&mut lineup, 7);
+        assert_eq!(lineup.leader().name, "Ania");
+        lineup.players_mut()[1].handicap = Some(3);
+        assert_eq!(lineup.leader().name, "Bartek");
+    }
+}
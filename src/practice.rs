@@ -0,0 +1,108 @@
+use std::fmt;
+
+use super::phys::PinSet;
+use super::variant::Variant;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PracticeError {
+    NoLeaves,
+    BadPin(String),
+    NotInRack { pin: u8, variant: &'static str },
+}
+
+impl fmt::Display for PracticeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PracticeError::NoLeaves => write!(f, "no leaves to practice"),
+            PracticeError::BadPin(pin) => write!(f, "'{pin}' is not a pin number"),
+            PracticeError::NotInRack { pin, variant } => write!(f, "{variant} has no pin {pin}"),
+        }
+    }
+}
+
+impl std::error::Error for PracticeError {}
+
+// Balls thrown at one leave and how many of them picked it up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Attempts {
+    pub thrown: u32,
+    pub converted: u32,
+}
+
+impl Attempts {
+    pub fn percentage(self) -> f32 {
+        if self.thrown == 0 {
+            0.0
+        } else {
+            self.converted as f32 * 100.0 / self.thrown as f32
+        }
+    }
+}
+
+impl fmt::Display for Attempts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} ({:.0}%)", self.converted, self.thrown, self.percentage())
+    }
+}
+
+// Spare practice: the same leave is set up after every ball until the bowler moves on to the next one
+pub struct Practice {
+    leaves: Vec<(PinSet, Attempts)>,
+    current: usize,
+}
+
+impl Practice {
+    // Leaves are given by their pin numbers and separated with commas, e.g. "10,4-7-10"
+    pub fn parse(spec: &str, variant: &Variant) -> Result<Self, PracticeError> {
+        let leaves = spec
+            .split(',')
+            .filter(|leave| !leave.trim().is_empty())
+            .map(|leave| parse_leave(leave, variant).map(|pins| (pins, Attempts::default())))
+            .collect::<Result<Vec<_>, _>>()?;
+        if leaves.is_empty() {
+            return Err(PracticeError::NoLeaves);
+        }
+        Ok(Self { leaves, current: 0 })
+    }
+
+    pub fn leave(&self) -> PinSet {
+        self.leaves[self.current].0
+    }
+
+    pub fn next_leave(&mut self) -> PinSet {
+        self.current = (self.current + 1) % self.leaves.len();
+        self.leave()
+    }
+
+    // Counts a ball at the current leave, it is converted when nothing is left standing
+    pub fn record(&mut self, standing: PinSet) -> Attempts {
+        let attempts = &mut self.leaves[self.current].1;
+        attempts.thrown += 1;
+        if standing.len() == 0 {
+            attempts.converted += 1;
+        }
+        *attempts
+    }
+}
+
+fn parse_leave(spec: &str, variant: &Variant) -> Result<PinSet, PracticeError> {
+    let mut pins = PinSet::default();
+    for pin in spec.trim().split('-') {
+        let number = pin.trim().parse::<u8>().map_err(|_| PracticeError::BadPin(pin.to_string()))?;
+        if !variant.rack.iter().any(|spot| spot.number == number) {
+            return Err(PracticeError::NotInRack { pin: number, variant: variant.name });
+        }
+        pins.insert(number);
+    }
+    Ok(pins)
+}
+
+impl fmt::Display for Practice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (leave, attempts)) in self.leaves.iter().enumerate() {
+            let marker = if index == self.current { ">" } else { " " };
+            writeln!(f, "{marker} {:<14} {attempts}", leave.to_string())?;
+        }
+        Ok(())
+    }
+}
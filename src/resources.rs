@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::io::{BufReader, Cursor};
 
 use wgpu::util::DeviceExt;
@@ -6,6 +7,8 @@ use wgpu::util::DeviceExt;
 pub mod model;
 use model::{Instance};
 use model::texture::Texture;
+use super::lane::Block;
+use super::phys::EntityId;
 pub async fn load_string(file_name: &str) -> anyhow::Result<String> {
     let path = std::path::Path::new(env!("OUT_DIR"))
         .join("res")
@@ -125,9 +128,66 @@ pub async fn load_model(
     Ok(model::Model { meshes, materials })
 }
 
+//Builds the lane mesh out of the same blocks the lane colliders are made of
+pub async fn build_lane_model(
+    blocks: &[Block],
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<model::Model> {
+    let mut vertices: Vec<model::ModelVertex> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for block in blocks {
+        let c = block.center;
+        let h = block.half_extents;
+        for axis in 0..3 {
+            for sign in [1.0f32, -1.0] {
+                //u x v has to point along the face normal for counter-clockwise winding
+                let (mut u, mut v) = ((axis + 1) % 3, (axis + 2) % 3);
+                if sign < 0.0 {
+                    std::mem::swap(&mut u, &mut v);
+                }
+                let mut normal = [0.0; 3];
+                normal[axis] = sign;
+                let base = vertices.len() as u32;
+                for (su, sv, tex_coords) in [(-1.0, -1.0, [0.0, 0.0]), (1.0, -1.0, [1.0, 0.0]), (1.0, 1.0, [1.0, 1.0]), (-1.0, 1.0, [0.0, 1.0])] {
+                    let mut position = c;
+                    position[axis] += sign * h[axis];
+                    position[u] += su * h[u];
+                    position[v] += sv * h[v];
+                    vertices.push(model::ModelVertex { position, tex_coords, normal });
+                }
+                indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+            }
+        }
+    }
+
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Lane Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Lane Index Buffer"),
+        contents: bytemuck::cast_slice(&indices),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+    let diffuse_texture = load_texture("darkwood.jpg", device, queue).await?;
+    Ok(model::Model {
+        meshes: vec![model::Mesh {
+            name: "lane".to_string(),
+            vertex_buffer,
+            index_buffer,
+            num_elements: indices.len() as u32,
+            material: 0,
+        }],
+        materials: vec![model::Material { name: "darkwood".to_string(), diffuse_texture }],
+    })
+}
+
 pub struct UniformPool {
     label: &'static str,
-    pub buffers: Vec<wgpu::Buffer>,
+    // one buffer for every entity drawn, made the first time it is updated
+    pub buffers: HashMap<EntityId, wgpu::Buffer>,
     size: u64,
 }
 
@@ -135,31 +195,25 @@ impl UniformPool {
     pub fn new(label: &'static str, size: u64) -> Self {
         Self {
             label,
-            buffers: Vec::new(),
+            buffers: HashMap::new(),
             size,
         }
     }
 
-    pub fn alloc_buffers(&mut self, count: usize, device: &wgpu::Device) {
-        // We reset the buffers each time we allocate
-        // TODO: Ideally we should keep track of the object it belongs to,
-        // so we can add/remove objects (and their uniform buffers) dynamically
-        self.buffers = Vec::new();
-
-        for _ in 0..count {
-            let local_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some(&self.label),
+    pub fn update_uniform<T: bytemuck::Pod>(&mut self, entity: EntityId, data: T, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let buffer = self.buffers.entry(entity).or_insert_with(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(self.label),
                 size: self.size,
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 mapped_at_creation: false,
-            });
-            self.buffers.push(local_uniform_buffer);
-        }
+            })
+        });
+        queue.write_buffer(buffer, 0, bytemuck::cast_slice(&[data]));
     }
 
-    pub fn update_uniform<T: bytemuck::Pod>(&self, index: usize, data: T, queue: &wgpu::Queue) {
-        if &self.buffers.len() > &0 {
-            queue.write_buffer(&self.buffers[index], 0, bytemuck::cast_slice(&[data]));
-        }
+    // the entity is gone, so is its buffer
+    pub fn free(&mut self, entity: EntityId) {
+        self.buffers.remove(&entity);
     }
-}
\ No newline at end of file
+}
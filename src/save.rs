@@ -0,0 +1,159 @@
+use std::path::PathBuf;
+
+use nalgebra::{Isometry3, Quaternion, Translation3, UnitQuaternion};
+
+use super::history::{self, HistoryError};
+use super::players::{Lineup, Player};
+use super::scoring::ScoreSheet;
+use super::variant::{Variant, VariantKind};
+
+// The game in progress, saved every time the rack is set so closing the window loses at most the
+// ball that was rolling. It is kept like a finished game in the history, but with the players
+// given the way they are on the command line and the pins that are on the deck:
+//
+//   version 1
+//   variant ten
+//   oil house
+//   player Ania:15
+//   handicap 12
+//   ball 1 9 10
+//   pin 10 0.4572 0.19 18.288 1 0 0 0
+//
+// A pin line gives the pin's position and its rotation as a quaternion (w, i, j, k), in lane coordinates.
+const FILE: &str = "saved_game.txt";
+
+pub struct SavedGame {
+    pub variant: VariantKind,
+    // the pattern file, not the name of the pattern
+    pub oil: String,
+    pub players: Vec<Player>,
+    pub pins: Vec<(u8, Isometry3<f32>)>,
+}
+
+impl SavedGame {
+    pub fn new(lineup: &Lineup, variant: VariantKind, oil: &str, pins: Vec<(u8, Isometry3<f32>)>) -> Self {
+        Self { variant, oil: oil.to_string(), players: lineup.players().to_vec(), pins }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, HistoryError> {
+        let mut saved =
+            Self { variant: VariantKind::TenPin, oil: String::new(), players: Vec::new(), pins: Vec::new() };
+        for line in history::lines(text)? {
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            let bad_value = || HistoryError::BadValue { key: key.to_string(), value: value.to_string() };
+            match (key, saved.players.last_mut()) {
+                ("variant", None) => saved.variant = VariantKind::parse(value).ok_or_else(bad_value)?,
+                ("oil", None) => saved.oil = value.to_string(),
+                ("player", _) => {
+                    let mut player = Player::parse(value, saved.players.len()).map_err(|_| bad_value())?;
+                    player.score = ScoreSheet::with_rules(Variant::new(saved.variant).rules);
+                    saved.players.push(player);
+                }
+                ("handicap", Some(player)) => player.handicap = Some(value.parse().map_err(|_| bad_value())?),
+                ("ball", Some(player)) => history::read_ball(&mut player.score, &player.name, value)?,
+                ("pin", _) => saved.pins.push(parse_pin(value).ok_or_else(bad_value)?),
+                _ => return Err(HistoryError::UnknownKey(key.to_string())),
+            }
+        }
+        Ok(saved)
+    }
+
+    // The game in the format parse reads
+    pub fn to_text(&self) -> String {
+        let mut text = format!("version {}\nvariant {}\noil {}\n", history::VERSION, self.variant.id(), self.oil);
+        for player in &self.players {
+            text.push_str(&format!("player {}\n", player.to_arg()));
+            if let Some(handicap) = player.handicap {
+                text.push_str(&format!("handicap {handicap}\n"));
+            }
+            text.push_str(&history::balls_to_text(&player.score));
+        }
+        for (pin, position) in &self.pins {
+            let (t, r) = (position.translation.vector, position.rotation);
+            text.push_str(&format!("pin {pin} {} {} {} {} {} {} {}\n", t.x, t.y, t.z, r.w, r.i, r.j, r.k));
+        }
+        text
+    }
+}
+
+// "10 0.4572 0.19 18.288 1 0 0 0"
+fn parse_pin(value: &str) -> Option<(u8, Isometry3<f32>)> {
+    let mut fields = value.split_whitespace();
+    let pin = fields.next()?.parse().ok()?;
+    let numbers = fields.map(|field| field.parse::<f32>().ok()).collect::<Option<Vec<f32>>>()?;
+    let [x, y, z, w, i, j, k] = numbers[..] else { return None };
+    //written from a unit quaternion, normalizing it again would move the pin by a rounding error
+    let rotation = UnitQuaternion::new_unchecked(Quaternion::new(w, i, j, k));
+    Some((pin, Isometry3::from_parts(Translation3::new(x, y, z), rotation)))
+}
+
+// Where the game in progress is kept, None without a data directory
+pub fn path() -> Option<PathBuf> {
+    history::data_dir().map(|dir| dir.join(history::DATA_DIR).join(FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::super::phys::PinSet;
+    use super::super::players::LineupError;
+    use super::*;
+
+    #[test]
+    fn a_game_in_progress_comes_back_as_it_was_left() {
+        let args = ["Ania:15", "Robot@180:13", "Team=Ola,Ewa@120"].map(String::from);
+        let mut lineup = Lineup::from_args(args.into_iter()).unwrap();
+        lineup.players_mut()[0].handicap = Some(12);
+        // a strike, a split left open and the first ball of the third frame
+        for player in lineup.players_mut() {
+            let mut split = PinSet::default();
+            split.insert(7);
+            split.insert(10);
+            player.score.roll(10).unwrap();
+            player.score.mark_leave(PinSet::default());
+            player.score.roll(8).unwrap();
+            player.score.mark_leave(split);
+            player.score.mark_split();
+            player.score.roll(0).unwrap();
+            player.score.mark_leave(split);
+            player.score.roll(9).unwrap();
+            let mut five = PinSet::default();
+            five.insert(5);
+            player.score.mark_leave(five);
+        }
+        let tilted = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 1.2);
+        let pins = vec![
+            (10, Isometry3::from_parts(Translation3::new(0.4572, 0.19, 18.288), UnitQuaternion::identity())),
+            (3, Isometry3::from_parts(Translation3::new(-0.1524, 0.06, 18.0), tilted)),
+        ];
+        let saved = SavedGame::new(&lineup, VariantKind::TenPin, "res/patterns/house.txt", pins.clone());
+        let loaded = SavedGame::parse(&saved.to_text()).unwrap();
+        assert_eq!(loaded.variant, VariantKind::TenPin);
+        assert_eq!(loaded.oil, "res/patterns/house.txt");
+        assert_eq!(loaded.pins, pins);
+        assert_eq!(loaded.players.len(), 3);
+        for (loaded, saved) in loaded.players.iter().zip(lineup.players()) {
+            assert_eq!(loaded.to_arg(), saved.to_arg());
+            assert_eq!(loaded.handicap, saved.handicap);
+            assert_eq!(loaded.score, saved.score);
+            assert_eq!(loaded.members.len(), saved.members.len());
+        }
+        assert_eq!(loaded.players[2].members[1].bot.as_ref().map(|bot| bot.skill.average), Some(120));
+        let resumed = Lineup::resume(loaded.players).unwrap();
+        assert_eq!(resumed.active().score.current_frame(), 2);
+        assert_eq!(resumed.active().score.pins_standing(), 1);
+    }
+
+    #[test]
+    fn names_that_would_break_the_file_are_refused() {
+        for player in ["Ann,a", "Te:am=Ola", "Te@m=Ola,Ewa"] {
+            let text = format!("version {}\nvariant ten\noil house\nplayer {player}\n", history::VERSION);
+            assert!(matches!(SavedGame::parse(&text), Err(HistoryError::BadValue { .. })), "{player:?}");
+        }
+        // nor does a name that would be two lines get into the file
+        let name = Player::parse("Ania\nball 1 10 -", 0).map(|player| player.name);
+        assert_eq!(name, Err(LineupError::BadName("Ania\nball 1 10 -".to_string())));
+    }
+}
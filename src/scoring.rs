@@ -0,0 +1,480 @@
+use std::fmt;
+
+use super::phys::PinSet;
+
+// Ten-frame scoring. One ScoreSheet per bowler, fed with the number of pins (or their point
+// value) knocked down by every ball, or with what is left standing on the deck.
+// A strike earns the next two balls as a bonus, a spare the next one.
+pub const FRAMES: usize = 10;
+pub const PINS: u8 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    // what a full rack is worth
+    pub rack_value: u8,
+    pub balls_per_frame: u8,
+}
+
+impl Rules {
+    pub const TEN_PIN: Rules = Rules { rack_value: PINS, balls_per_frame: 2 };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreError {
+    GameOver,
+    TooManyPins { standing: u8, knocked: u8 },
+}
+
+impl fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreError::GameOver => write!(f, "the game is already over"),
+            ScoreError::TooManyPins { standing, knocked } => {
+                write!(f, "cannot knock down {knocked} pins, only {standing} are standing")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScoreError {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub rolls: Vec<u8>,
+    // balls of this frame that left a split
+    pub splits: Vec<usize>,
+    // pins left standing after every ball, for the game history
+    pub leaves: Vec<PinSet>,
+}
+
+impl Frame {
+    pub fn is_strike(&self, rules: Rules) -> bool {
+        self.rolls.first() == Some(&rules.rack_value)
+    }
+    pub fn is_spare(&self, rules: Rules) -> bool {
+        !self.is_strike(rules) && self.rolls.len() >= 2 && self.rolls[0] + self.rolls[1] == rules.rack_value
+    }
+}
+
+// What the pinsetter and the game flow need to know after a ball
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RollResult {
+    pub frame: usize,
+    pub knocked: u8,
+    pub strike: bool,
+    pub spare: bool,
+    pub frame_complete: bool,
+    // the next ball is thrown at a full rack
+    pub rerack: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreSheet {
+    rules: Rules,
+    frames: Vec<Frame>,
+}
+
+impl Default for ScoreSheet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScoreSheet {
+    pub fn new() -> Self {
+        Self::with_rules(Rules::TEN_PIN)
+    }
+
+    pub fn with_rules(rules: Rules) -> Self {
+        Self { rules, frames: vec![Frame::default()] }
+    }
+
+    fn frame_complete(&self, index: usize, frame: &Frame) -> bool {
+        let balls = frame.rolls.len();
+        if index < FRAMES - 1 {
+            return balls == self.rules.balls_per_frame as usize || self.rack_cleared(frame);
+        }
+        // tenth frame: a mark earns the extra balls, three at most
+        let marked = frame.is_strike(self.rules) || frame.is_spare(self.rules);
+        balls == 3 || balls == self.rules.balls_per_frame as usize && !marked
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.frames.len() == FRAMES && self.frame_complete(FRAMES - 1, &self.frames[FRAMES - 1])
+    }
+
+    // Index of the frame that receives the next ball
+    pub fn current_frame(&self) -> usize {
+        self.frames.len() - 1
+    }
+
+    // Pins (or points) on the deck in front of the next ball
+    pub fn pins_standing(&self) -> u8 {
+        self.standing_after(&self.frames[self.current_frame()])
+    }
+
+    // What is left on the deck after the frame's balls and how many balls have been thrown
+    // at it. In the tenth frame the rack is reset every time it is cleared
+    fn rack_after(&self, frame: &Frame) -> (u8, usize) {
+        let full = self.rules.rack_value;
+        frame.rolls.iter().fold((full, 0), |(standing, balls), &pins| match standing - pins {
+            0 => (full, 0),
+            left => (left, balls + 1),
+        })
+    }
+
+    // The next ball is the first at a fresh rack: the first of a frame, or one after a tenth-frame rerack
+    pub fn first_ball_at_rack(&self) -> bool {
+        self.rack_after(&self.frames[self.current_frame()]).1 == 0
+    }
+
+    fn standing_after(&self, frame: &Frame) -> u8 {
+        self.rack_after(frame).0
+    }
+
+    // the last ball took out whatever was left, a miss at a full rack doesn't count
+    fn rack_cleared(&self, frame: &Frame) -> bool {
+        !frame.rolls.is_empty() && self.rack_after(frame).1 == 0
+    }
+
+    pub fn roll(&mut self, knocked: u8) -> Result<RollResult, ScoreError> {
+        if self.is_complete() {
+            return Err(ScoreError::GameOver);
+        }
+        let standing = self.pins_standing();
+        if knocked > standing {
+            return Err(ScoreError::TooManyPins { standing, knocked });
+        }
+
+        let index = self.current_frame();
+        let (_, rack_balls) = self.rack_after(&self.frames[index]);
+        // a rack cleared by the third ball is neither
+        let strike = rack_balls == 0 && knocked == standing;
+        let spare = rack_balls == 1 && knocked == standing;
+        self.frames[index].rolls.push(knocked);
+        let frame = &self.frames[index];
+        let frame_complete = self.frame_complete(index, frame);
+        let rerack = frame_complete || self.rack_cleared(frame);
+        if frame_complete && index < FRAMES - 1 {
+            self.frames.push(Frame::default());
+        }
+        Ok(RollResult { frame: index, knocked, strike, spare, frame_complete, rerack })
+    }
+
+    // Flags the last ball as having left a split
+    pub fn mark_split(&mut self) {
+        if let Some(frame) = self.frames.iter_mut().rev().find(|frame| !frame.rolls.is_empty()) {
+            let ball = frame.rolls.len() - 1;
+            frame.splits.push(ball);
+        }
+    }
+
+    // Keeps the pins the last ball left standing
+    pub fn mark_leave(&mut self, standing: PinSet) {
+        if let Some(frame) = self.frames.iter_mut().rev().find(|frame| !frame.rolls.is_empty()) {
+            frame.leaves.push(standing);
+        }
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    // Feeds the sheet with the pins left standing after the ball has settled
+    pub fn record_standing(&mut self, standing: u8) -> Result<RollResult, ScoreError> {
+        let before = self.pins_standing();
+        if standing > before {
+            return Err(ScoreError::TooManyPins { standing: before, knocked: 0 });
+        }
+        self.roll(before - standing)
+    }
+
+    // Running totals; None while a frame still waits for its bonus balls
+    pub fn frame_scores(&self) -> [Option<u16>; FRAMES] {
+        let rolls: Vec<u16> = self
+            .frames
+            .iter()
+            .flat_map(|frame| frame.rolls.iter().map(|&pins| pins as u16))
+            .collect();
+        let mut scores = [None; FRAMES];
+        let mut total = 0;
+        let mut first_roll = 0;
+        for (index, frame) in self.frames.iter().enumerate() {
+            let needed = if frame.is_strike(self.rules) || frame.is_spare(self.rules) { 3 } else { frame.rolls.len() };
+            if !self.frame_complete(index, frame) || rolls.len() < first_roll + needed {
+                break;
+            }
+            total += rolls[first_roll..first_roll + needed].iter().sum::<u16>();
+            scores[index] = Some(total);
+            first_roll += frame.rolls.len();
+        }
+        scores
+    }
+
+    pub fn total(&self) -> u16 {
+        self.frame_scores().iter().flatten().last().copied().unwrap_or(0)
+    }
+}
+
+// Scorecard marks: X for a strike, / for a spare, - for a miss, a split count goes in brackets
+fn mark(pins: u8, standing: u8, rack_balls: usize) -> String {
+    match pins {
+        0 => "-".to_string(),
+        p if p == standing && rack_balls == 0 => "X".to_string(),
+        p if p == standing && rack_balls == 1 => "/".to_string(),
+        p => p.to_string(),
+    }
+}
+
+impl fmt::Display for ScoreSheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scores = self.frame_scores();
+        let full = self.rules.rack_value;
+        // counts can take two digits when pins are worth points
+        let separator = if full > PINS { " " } else { "" };
+        for frame in &self.frames {
+            let mut standing = full;
+            let mut rack_balls = 0;
+            let mut marks = Vec::new();
+            for (ball, &pins) in frame.rolls.iter().enumerate() {
+                let mark = mark(pins, standing, rack_balls);
+                if frame.splits.contains(&ball) {
+                    marks.push(format!("({mark})"));
+                } else {
+                    marks.push(mark);
+                }
+                standing -= pins;
+                rack_balls += 1;
+                if standing == 0 {
+                    standing = full;
+                    rack_balls = 0;
+                }
+            }
+            let marks = marks.join(separator);
+            write!(f, "|{marks:^5}")?;
+        }
+        writeln!(f, "|")?;
+        for score in scores.iter().take(self.frames.len()) {
+            match score {
+                Some(total) => write!(f, "|{total:^5}")?,
+                None => write!(f, "|     ")?,
+            }
+        }
+        write!(f, "|")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::variant::{Variant, VariantKind};
+    use super::*;
+
+    fn play(sheet: &mut ScoreSheet, rolls: &[u8]) {
+        for &pins in rolls {
+            sheet.roll(pins).unwrap();
+        }
+    }
+
+    fn three_ball_rules() -> Vec<Rules> {
+        [VariantKind::Candlepin, VariantKind::Duckpin, VariantKind::FivePin]
+            .into_iter()
+            .map(|kind| Variant::new(kind).rules)
+            .collect()
+    }
+
+    #[test]
+    fn gutter_game() {
+        let mut sheet = ScoreSheet::new();
+        play(&mut sheet, &[0; 20]);
+        assert!(sheet.is_complete());
+        assert_eq!(sheet.frame_scores(), [Some(0); FRAMES]);
+        assert_eq!(sheet.total(), 0);
+    }
+
+    #[test]
+    fn all_spares() {
+        let mut sheet = ScoreSheet::new();
+        play(&mut sheet, &[5; 21]);
+        assert!(sheet.is_complete());
+        assert_eq!(sheet.frame_scores()[0], Some(15));
+        assert_eq!(sheet.total(), 150);
+    }
+
+    #[test]
+    fn perfect_game() {
+        let mut sheet = ScoreSheet::new();
+        play(&mut sheet, &[10; 12]);
+        assert!(sheet.is_complete());
+        assert_eq!(sheet.total(), 300);
+        assert_eq!(sheet.roll(10), Err(ScoreError::GameOver));
+        assert_eq!(sheet.to_string().lines().next(), Some("|  X  |  X  |  X  |  X  |  X  |  X  |  X  |  X  |  X  | XXX |"));
+    }
+
+    #[test]
+    fn strike_waits_for_its_bonus() {
+        let mut sheet = ScoreSheet::new();
+        play(&mut sheet, &[10, 3]);
+        assert_eq!(sheet.frame_scores()[0], None);
+        play(&mut sheet, &[4]);
+        assert_eq!(sheet.frame_scores()[..2], [Some(17), Some(24)]);
+    }
+
+    #[test]
+    fn tenth_frame_strike_earns_two_balls() {
+        let mut sheet = ScoreSheet::new();
+        play(&mut sheet, &[0; 18]);
+        let strike = sheet.roll(10).unwrap();
+        assert!(strike.strike && strike.rerack && !strike.frame_complete);
+        play(&mut sheet, &[3]);
+        assert!(!sheet.is_complete());
+        assert_eq!(sheet.pins_standing(), 7);
+        let last = sheet.roll(4).unwrap();
+        assert!(last.frame_complete);
+        assert!(sheet.is_complete());
+        assert_eq!(sheet.total(), 17);
+    }
+
+    #[test]
+    fn tenth_frame_spare_earns_one_ball() {
+        let mut sheet = ScoreSheet::new();
+        play(&mut sheet, &[0; 18]);
+        play(&mut sheet, &[7]);
+        let spare = sheet.roll(3).unwrap();
+        assert!(spare.spare && spare.rerack && !spare.frame_complete);
+        assert_eq!(sheet.pins_standing(), 10);
+        play(&mut sheet, &[5]);
+        assert!(sheet.is_complete());
+        assert_eq!(sheet.total(), 15);
+    }
+
+    #[test]
+    fn open_tenth_frame_ends_after_two_balls() {
+        let mut sheet = ScoreSheet::new();
+        play(&mut sheet, &[0; 18]);
+        play(&mut sheet, &[3, 4]);
+        assert!(sheet.is_complete());
+        assert_eq!(sheet.roll(1), Err(ScoreError::GameOver));
+    }
+
+    #[test]
+    fn too_many_pins() {
+        let mut sheet = ScoreSheet::new();
+        play(&mut sheet, &[7]);
+        assert_eq!(sheet.roll(4), Err(ScoreError::TooManyPins { standing: 3, knocked: 4 }));
+        assert_eq!(sheet.record_standing(4), Err(ScoreError::TooManyPins { standing: 3, knocked: 0 }));
+        assert_eq!(ScoreSheet::new().roll(11), Err(ScoreError::TooManyPins { standing: 10, knocked: 11 }));
+        // a rejected ball leaves the sheet as it was
+        assert_eq!(sheet.frames()[0].rolls, vec![7]);
+    }
+
+    #[test]
+    fn record_standing_counts_what_fell() {
+        let mut sheet = ScoreSheet::new();
+        let first = sheet.record_standing(2).unwrap();
+        assert_eq!(first.knocked, 8);
+        let second = sheet.record_standing(0).unwrap();
+        assert!(second.spare && second.frame_complete);
+    }
+
+    #[test]
+    fn first_ball_at_rack() {
+        let mut sheet = ScoreSheet::new();
+        assert!(sheet.first_ball_at_rack());
+        // a miss leaves the rack full, but the next ball is a second ball
+        play(&mut sheet, &[0]);
+        assert!(!sheet.first_ball_at_rack());
+        play(&mut sheet, &[3]);
+        assert!(sheet.first_ball_at_rack());
+        play(&mut sheet, &[0; 16]);
+        play(&mut sheet, &[10]);
+        assert!(sheet.first_ball_at_rack());
+        play(&mut sheet, &[3]);
+        assert!(!sheet.first_ball_at_rack());
+    }
+
+    #[test]
+    fn three_balls_a_frame() {
+        for rules in three_ball_rules() {
+            let full = rules.rack_value;
+            let mut sheet = ScoreSheet::with_rules(rules);
+            play(&mut sheet, &[1, 1]);
+            assert_eq!(sheet.current_frame(), 0);
+            let third = sheet.roll(1).unwrap();
+            assert!(third.frame_complete && !third.strike && !third.spare);
+            assert_eq!(sheet.frame_scores()[0], Some(3));
+            // a rack cleared by the third ball is neither a strike nor a spare and earns no bonus
+            let third = {
+                play(&mut sheet, &[1, 1]);
+                sheet.roll(full - 2).unwrap()
+            };
+            assert!(third.frame_complete && third.rerack && !third.strike && !third.spare);
+            assert_eq!(sheet.frame_scores()[1], Some(3 + u16::from(full)));
+            // a spare takes two balls and ends the frame early
+            let spare = {
+                play(&mut sheet, &[1]);
+                sheet.roll(full - 1).unwrap()
+            };
+            assert!(spare.spare && spare.frame_complete);
+            play(&mut sheet, &[2, 0, 0]);
+            assert_eq!(sheet.frame_scores()[2], Some(3 + 2 * u16::from(full) + 2));
+            let strike = sheet.roll(full).unwrap();
+            assert!(strike.strike && strike.frame_complete);
+        }
+    }
+
+    #[test]
+    fn three_ball_tenth_frame_reracks() {
+        for rules in three_ball_rules() {
+            let full = rules.rack_value;
+            let mut sheet = ScoreSheet::with_rules(rules);
+            play(&mut sheet, &[0; 27]);
+            assert_eq!(sheet.current_frame(), FRAMES - 1);
+            play(&mut sheet, &[full]);
+            assert_eq!(sheet.rack_after(&sheet.frames()[FRAMES - 1]), (full, 0));
+            play(&mut sheet, &[4]);
+            assert_eq!(sheet.rack_after(&sheet.frames()[FRAMES - 1]), (full - 4, 1));
+            assert!(!sheet.is_complete());
+            play(&mut sheet, &[full - 4]);
+            assert_eq!(sheet.rack_after(&sheet.frames()[FRAMES - 1]), (full, 0));
+            assert!(sheet.is_complete());
+            assert_eq!(sheet.total(), 2 * u16::from(full));
+        }
+    }
+
+    #[test]
+    fn three_ball_open_tenth_frame_takes_three_balls() {
+        for rules in three_ball_rules() {
+            let mut sheet = ScoreSheet::with_rules(rules);
+            play(&mut sheet, &[0; 27]);
+            play(&mut sheet, &[1, 1]);
+            assert!(!sheet.is_complete());
+            play(&mut sheet, &[1]);
+            assert!(sheet.is_complete());
+        }
+    }
+
+    #[test]
+    fn marks() {
+        let mut sheet = ScoreSheet::new();
+        play(&mut sheet, &[10, 7, 3, 0, 9, 8]);
+        sheet.mark_split();
+        play(&mut sheet, &[1]);
+        let card = sheet.to_string();
+        let mut lines = card.lines();
+        assert_eq!(lines.next(), Some("|  X  | 7/  | -9  |(8)1 |     |"));
+        assert_eq!(lines.next(), Some("| 20  | 30  | 39  | 48  |     |"));
+    }
+
+    #[test]
+    fn three_ball_marks() {
+        let candlepin = ScoreSheet::with_rules(Variant::new(VariantKind::Candlepin).rules);
+        let mut sheet = candlepin.clone();
+        play(&mut sheet, &[3, 0, 7, 4, 6]);
+        assert_eq!(sheet.to_string().lines().next(), Some("| 3-7 | 4/  |     |"));
+        // points of five-pin can take two digits, the marks are spaced apart
+        let mut sheet = ScoreSheet::with_rules(Variant::new(VariantKind::FivePin).rules);
+        play(&mut sheet, &[5, 10, 15, 2, 3, 0]);
+        assert_eq!(sheet.to_string().lines().next(), Some("| 5 / |  X  |2 3 -|     |"));
+        assert_eq!(sheet.frame_scores()[..3], [Some(30), Some(50), Some(55)]);
+    }
+}
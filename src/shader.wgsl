@@ -120,7 +120,6 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
 
     let result = (ambient_color + diffuse_color + specular_color) * object_color.xyz;
 
-    return vec4<f32>(result, object_color.a);
-    // return locals.color * vec4<f32>(result, object_color.a);
+    return locals.color * vec4<f32>(result, object_color.a);
     // return vec4<f32>(0.0,0.2,0.8, 1.0);
 }
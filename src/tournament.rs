@@ -0,0 +1,487 @@
+use std::fmt;
+
+use super::players::{Lineup, Player, MAX_PLAYERS};
+
+// Finalists when the format doesn't say: the TV step-ladder has five, a bracket eight
+const LADDER_FINALISTS: usize = 5;
+const BRACKET_FINALISTS: usize = 8;
+// Seeds of one match are this far from those of the next, more than a lineup has bots
+const MATCH_SEED_STEP: u64 = 1 << 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TournamentError {
+    BadFormat(String),
+    NotEnoughEntrants(usize),
+}
+
+impl fmt::Display for TournamentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TournamentError::BadFormat(format) => write!(
+                f,
+                "bad tournament format '{format}', expected <qualifying games>:<ladder|bracket>[:<finalists>]"
+            ),
+            TournamentError::NotEnoughEntrants(count) => {
+                write!(f, "a tournament needs at least two entrants, {count} given")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TournamentError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finals {
+    // the lowest seed bowls the next one up, the winner moves up until the top seed
+    StepLadder,
+    // single elimination, the top seeds meet the bottom ones first
+    Bracket,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    pub qualifying_games: usize,
+    pub finals: Finals,
+    pub finalists: usize,
+}
+
+impl Format {
+    // "3:ladder", "2:bracket:4"
+    pub fn parse(spec: &str) -> Result<Self, TournamentError> {
+        let bad_format = || TournamentError::BadFormat(spec.to_string());
+        let mut parts = spec.split(':');
+        let qualifying_games = parts.next().and_then(|games| games.trim().parse().ok()).ok_or_else(bad_format)?;
+        let finals = match parts.next().map(str::trim) {
+            Some("ladder") => Finals::StepLadder,
+            Some("bracket") => Finals::Bracket,
+            _ => return Err(bad_format()),
+        };
+        let finalists = match parts.next() {
+            Some(count) => count.trim().parse().ok().filter(|count| *count >= 2).ok_or_else(bad_format)?,
+            None if finals == Finals::StepLadder => LADDER_FINALISTS,
+            None => BRACKET_FINALISTS,
+        };
+        if parts.next().is_some() {
+            return Err(bad_format());
+        }
+        Ok(Self { qualifying_games, finals, finalists })
+    }
+}
+
+// One game on the lane: the entrants (by their index) in lane order, higher seeds first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub label: String,
+    pub entrants: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stage {
+    // everybody bowls the qualifying games, a squad of at most a lane's worth at a time
+    Qualifying { game: usize, squad: usize },
+    // seeds run from the top seed down, the holder has won every match so far
+    StepLadder { seeds: Vec<usize>, opponent: usize, holder: usize },
+    // the entrants still in, paired off in order
+    Bracket { alive: Vec<usize>, pair: usize, winners: Vec<usize> },
+    Done { champion: usize },
+}
+
+// A tournament: qualifying games decide the seeds, then the finals decide the champion.
+// Every match is bowled as an ordinary game, its lineup comes from here and goes back with the scores.
+pub struct Tournament {
+    entrants: Vec<Player>,
+    format: Format,
+    stage: Stage,
+    // qualifying pinfall of every entrant
+    pinfall: Vec<u32>,
+    results: Vec<String>,
+    // bots of every match are seeded from this, a match further on from it
+    seed: Option<u64>,
+}
+
+impl Tournament {
+    pub fn new(entrants: Vec<Player>, format: Format) -> Result<Self, TournamentError> {
+        if entrants.len() < 2 {
+            return Err(TournamentError::NotEnoughEntrants(entrants.len()));
+        }
+        let mut tournament = Self {
+            pinfall: vec![0; entrants.len()],
+            entrants,
+            format,
+            stage: Stage::Qualifying { game: 0, squad: 0 },
+            results: Vec::new(),
+            seed: None,
+        };
+        if format.qualifying_games == 0 {
+            tournament.start_finals();
+        }
+        Ok(tournament)
+    }
+
+    fn squads(&self) -> usize {
+        self.entrants.len().div_ceil(MAX_PLAYERS)
+    }
+
+    pub fn current_match(&self) -> Option<Match> {
+        match &self.stage {
+            Stage::Qualifying { game, squad } => {
+                let mut label = format!("Qualifying game {} of {}", game + 1, self.format.qualifying_games);
+                if self.squads() > 1 {
+                    label.push_str(&format!(", squad {}", squad + 1));
+                }
+                let start = squad * MAX_PLAYERS;
+                let end = (start + MAX_PLAYERS).min(self.entrants.len());
+                Some(Match { label, entrants: (start..end).collect() })
+            }
+            Stage::StepLadder { seeds, opponent, holder } => {
+                let label = if *opponent == 0 {
+                    "Step-ladder title match".to_string()
+                } else {
+                    format!("Step-ladder match {}", seeds.len() - 1 - opponent)
+                };
+                Some(Match { label, entrants: vec![seeds[*opponent], *holder] })
+            }
+            Stage::Bracket { alive, pair, .. } => {
+                let label = match alive.len() {
+                    2 => "Final".to_string(),
+                    4 => format!("Semifinal {}", pair + 1),
+                    8 => format!("Quarterfinal {}", pair + 1),
+                    count => format!("Round of {count}, match {}", pair + 1),
+                };
+                Some(Match { label, entrants: vec![alive[2 * pair], alive[2 * pair + 1]] })
+            }
+            Stage::Done { .. } => None,
+        }
+    }
+
+    // The lineup that bowls the current match
+    pub fn lineup(&self) -> Option<Lineup> {
+        let entrants = self.current_match()?.entrants;
+        let mut lineup = Lineup::new(entrants.iter().map(|&entrant| self.entrants[entrant].clone()).collect()).ok()?;
+        if let Some(seed) = self.seed {
+            lineup.seed_bots(seed.wrapping_add(MATCH_SEED_STEP * self.results.len() as u64));
+        }
+        Some(lineup)
+    }
+
+    // Bots bowl the same tournament again for the same seed
+    pub fn seed_bots(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    // Takes the scores of the finished match, the lineup is in the order lineup() gave it
+    pub fn record(&mut self, lineup: &Lineup) {
+        let Some(current) = self.current_match() else { return };
+        let scores: Vec<u16> = lineup.players().iter().map(Player::total_with_handicap).collect();
+        let line: Vec<String> = current
+            .entrants
+            .iter()
+            .zip(&scores)
+            .map(|(&entrant, score)| format!("{} {score}", self.entrants[entrant].name))
+            .collect();
+        self.results.push(format!("{}: {}", current.label, line.join(", ")));
+        //a tie goes to the higher seed, who is listed first
+        let winner = current
+            .entrants
+            .iter()
+            .zip(&scores)
+            .rev()
+            .max_by_key(|(_, score)| **score)
+            .map(|(&entrant, _)| entrant)
+            .unwrap_or(current.entrants[0]);
+        let squads = self.squads();
+        let mut qualified = false;
+        match &mut self.stage {
+            Stage::Qualifying { game, squad } => {
+                for (&entrant, &score) in current.entrants.iter().zip(&scores) {
+                    self.pinfall[entrant] += score as u32;
+                }
+                *squad += 1;
+                if *squad == squads {
+                    *squad = 0;
+                    *game += 1;
+                }
+                qualified = *game == self.format.qualifying_games;
+            }
+            Stage::StepLadder { opponent, holder, .. } => {
+                *holder = winner;
+                if *opponent == 0 {
+                    self.stage = Stage::Done { champion: winner };
+                } else {
+                    *opponent -= 1;
+                }
+            }
+            Stage::Bracket { alive, pair, winners } => {
+                winners.push(winner);
+                *pair += 1;
+                if 2 * *pair == alive.len() {
+                    if winners.len() == 1 {
+                        self.stage = Stage::Done { champion: winner };
+                    } else {
+                        *alive = std::mem::take(winners);
+                        *pair = 0;
+                    }
+                }
+            }
+            Stage::Done { .. } => {}
+        }
+        if qualified {
+            self.start_finals();
+        }
+    }
+
+    pub fn champion(&self) -> Option<&Player> {
+        match self.stage {
+            Stage::Done { champion } => Some(&self.entrants[champion]),
+            _ => None,
+        }
+    }
+
+    // Entrants from the best qualifier down, ties go to whoever was entered first
+    fn seeds(&self) -> Vec<usize> {
+        let mut seeds: Vec<usize> = (0..self.entrants.len()).collect();
+        seeds.sort_by_key(|&entrant| std::cmp::Reverse(self.pinfall[entrant]));
+        seeds
+    }
+
+    fn start_finals(&mut self) {
+        let mut seeds = self.seeds();
+        match self.format.finals {
+            Finals::StepLadder => {
+                seeds.truncate(self.format.finalists);
+                let holder = seeds[seeds.len() - 1];
+                self.stage = Stage::StepLadder { opponent: seeds.len() - 2, holder, seeds };
+            }
+            Finals::Bracket => {
+                //the biggest full bracket that fits
+                let mut size = 2;
+                while size * 2 <= self.format.finalists.min(seeds.len()) {
+                    size *= 2;
+                }
+                let alive = bracket_order(size).into_iter().map(|seed| seeds[seed - 1]).collect();
+                self.stage = Stage::Bracket { alive, pair: 0, winners: Vec::new() };
+            }
+        }
+    }
+}
+
+// Seeds in bracket order, 1 v 8, 4 v 5, 2 v 7, 3 v 6: the top two can only meet in the final
+fn bracket_order(size: usize) -> Vec<usize> {
+    let mut order = vec![1];
+    while order.len() < size {
+        let count = order.len() * 2;
+        order = order.iter().flat_map(|&seed| [seed, count + 1 - seed]).collect();
+    }
+    order
+}
+
+impl fmt::Display for Tournament {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.format.qualifying_games > 0 {
+            writeln!(f, "Qualifying:")?;
+            for (rank, entrant) in self.seeds().into_iter().enumerate() {
+                writeln!(f, "{:>3}. {:<16}{:>6}", rank + 1, self.entrants[entrant].name, self.pinfall[entrant])?;
+            }
+        }
+        for result in &self.results {
+            writeln!(f, "{result}")?;
+        }
+        match (self.champion(), self.current_match()) {
+            (Some(champion), _) => writeln!(f, "Champion: {}", champion.name),
+            (None, Some(next)) => {
+                let names: Vec<&str> =
+                    next.entrants.iter().map(|&entrant| self.entrants[entrant].name.as_str()).collect();
+                writeln!(f, "Next: {}, {}", next.label, names.join(" v "))
+            }
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lane::LaneSpec;
+    use super::super::phys::Physics;
+    use super::*;
+
+    // Entrants "1", "2", ... in the order they were entered
+    fn entrants(count: usize) -> Vec<Player> {
+        (0..count).map(|slot| Player::parse(&(slot + 1).to_string(), slot).unwrap()).collect()
+    }
+
+    // Bowls the current match, every entrant scores what `score` gives for their name (90 at most, no marks).
+    // Returns the names of the match in lane order
+    fn bowl(tournament: &mut Tournament, score: impl Fn(&str) -> u16) -> Vec<String> {
+        let mut lineup = tournament.lineup().unwrap();
+        for player in lineup.players_mut() {
+            let mut left = score(&player.name);
+            while !player.score.is_complete() {
+                let pins = left.min(9);
+                left -= pins;
+                player.score.roll(pins as u8).unwrap();
+                player.score.roll(0).unwrap();
+            }
+        }
+        tournament.record(&lineup);
+        lineup.players().iter().map(|player| player.name.clone()).collect()
+    }
+
+    // The better qualifier bowls the better game
+    fn seeded(name: &str) -> u16 {
+        90 - 5 * name.parse::<u16>().unwrap()
+    }
+
+    fn upset(name: &str) -> u16 {
+        5 * name.parse::<u16>().unwrap()
+    }
+
+    fn names(pairs: &[[&str; 2]]) -> Vec<Vec<String>> {
+        pairs.iter().map(|pair| pair.iter().map(|name| name.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn format_parse() {
+        let ladder = Format::parse("3:ladder").unwrap();
+        assert_eq!(ladder, Format { qualifying_games: 3, finals: Finals::StepLadder, finalists: LADDER_FINALISTS });
+        let bracket = Format::parse(" 2 : bracket : 4 ").unwrap();
+        assert_eq!(bracket, Format { qualifying_games: 2, finals: Finals::Bracket, finalists: 4 });
+        assert_eq!(Format::parse("0:bracket").unwrap().finalists, BRACKET_FINALISTS);
+        for spec in ["", "3", "x:ladder", "3:knockout", "3:ladder:1", "3:ladder:x", "3:ladder:4:2", "-1:ladder"] {
+            assert_eq!(Format::parse(spec), Err(TournamentError::BadFormat(spec.to_string())), "{spec}");
+        }
+    }
+
+    #[test]
+    fn bracket_order_keeps_the_top_seeds_apart() {
+        assert_eq!(bracket_order(2), [1, 2]);
+        assert_eq!(bracket_order(4), [1, 4, 2, 3]);
+        assert_eq!(bracket_order(8), [1, 8, 4, 5, 2, 7, 3, 6]);
+    }
+
+    #[test]
+    fn needs_two_entrants() {
+        let format = Format::parse("1:ladder").unwrap();
+        assert_eq!(Tournament::new(entrants(1), format).err(), Some(TournamentError::NotEnoughEntrants(1)));
+    }
+
+    #[test]
+    fn qualifying_seeds_the_step_ladder() {
+        let mut tournament = Tournament::new(entrants(6), Format::parse("2:ladder").unwrap()).unwrap();
+        for game in 0..2 {
+            assert_eq!(tournament.current_match().unwrap().label, format!("Qualifying game {} of 2", game + 1));
+            assert_eq!(bowl(&mut tournament, seeded).len(), 6);
+        }
+        // the sixth qualifier misses the finals, the fifth starts against the fourth
+        assert_eq!(tournament.current_match().unwrap().label, "Step-ladder match 1");
+        let matches: Vec<_> = (0..4).map(|_| bowl(&mut tournament, seeded)).collect();
+        assert_eq!(matches, names(&[["4", "5"], ["3", "4"], ["2", "3"], ["1", "2"]]));
+        assert_eq!(tournament.champion().unwrap().name, "1");
+        assert!(tournament.current_match().is_none() && tournament.lineup().is_none());
+    }
+
+    #[test]
+    fn the_step_ladder_winner_moves_up() {
+        let mut tournament = Tournament::new(entrants(5), Format::parse("1:ladder").unwrap()).unwrap();
+        bowl(&mut tournament, seeded);
+        let mut labels = Vec::new();
+        let mut matches = Vec::new();
+        while let Some(next) = tournament.current_match() {
+            labels.push(next.label);
+            matches.push(bowl(&mut tournament, upset));
+        }
+        assert_eq!(
+            labels,
+            ["Step-ladder match 1", "Step-ladder match 2", "Step-ladder match 3", "Step-ladder title match"]
+        );
+        assert_eq!(matches, names(&[["4", "5"], ["3", "5"], ["2", "5"], ["1", "5"]]));
+        assert_eq!(tournament.champion().unwrap().name, "5");
+    }
+
+    #[test]
+    fn bracket_of_eight() {
+        let mut tournament = Tournament::new(entrants(8), Format::parse("1:bracket").unwrap()).unwrap();
+        bowl(&mut tournament, seeded);
+        let mut labels = Vec::new();
+        let mut matches = Vec::new();
+        while let Some(next) = tournament.current_match() {
+            labels.push(next.label);
+            matches.push(bowl(&mut tournament, seeded));
+        }
+        assert_eq!(
+            labels,
+            [
+                "Quarterfinal 1",
+                "Quarterfinal 2",
+                "Quarterfinal 3",
+                "Quarterfinal 4",
+                "Semifinal 1",
+                "Semifinal 2",
+                "Final"
+            ]
+        );
+        let expected = [["1", "8"], ["4", "5"], ["2", "7"], ["3", "6"], ["1", "4"], ["2", "3"], ["1", "2"]];
+        assert_eq!(matches, names(&expected));
+        assert_eq!(tournament.champion().unwrap().name, "1");
+    }
+
+    #[test]
+    fn bracket_of_four_follows_the_winners() {
+        let mut tournament = Tournament::new(entrants(4), Format::parse("1:bracket").unwrap()).unwrap();
+        bowl(&mut tournament, seeded);
+        let matches: Vec<_> = (0..3).map(|_| bowl(&mut tournament, upset)).collect();
+        assert_eq!(matches, names(&[["1", "4"], ["2", "3"], ["4", "3"]]));
+        assert_eq!(tournament.champion().unwrap().name, "4");
+    }
+
+    #[test]
+    fn an_odd_entrant_count_leaves_the_lowest_seed_out() {
+        // the order of entry is not the seeding, the fifth entrant qualifies first
+        let mut tournament = Tournament::new(entrants(5), Format::parse("1:bracket").unwrap()).unwrap();
+        let score = |name: &str| if name == "5" { 90 } else { seeded(name) };
+        bowl(&mut tournament, score);
+        let matches: Vec<_> = (0..3).map(|_| bowl(&mut tournament, score)).collect();
+        assert_eq!(matches, names(&[["5", "3"], ["1", "2"], ["5", "1"]]));
+        assert_eq!(tournament.champion().unwrap().name, "5");
+    }
+
+    #[test]
+    fn a_tie_goes_to_the_higher_seed() {
+        // no qualifying, the order of entry is the seeding
+        let mut tournament = Tournament::new(entrants(2), Format::parse("0:ladder").unwrap()).unwrap();
+        assert_eq!(tournament.current_match().unwrap().label, "Step-ladder title match");
+        assert_eq!(bowl(&mut tournament, |_| 60), ["1", "2"]);
+        assert_eq!(tournament.champion().unwrap().name, "1");
+    }
+
+    #[test]
+    fn seeded_bots_bowl_the_same_tournament() {
+        let mut physics = Physics::new(LaneSpec::REGULATION);
+        physics.build_colliders();
+        let standing = physics.standing_pins();
+        let bots = || (0..3).map(|slot| Player::parse(&format!("Bot{slot}@180"), slot).unwrap()).collect();
+        let first_throws = |tournament: &Tournament| -> Vec<_> {
+            let mut lineup = tournament.lineup().unwrap();
+            lineup
+                .players_mut()
+                .iter_mut()
+                .map(|player| player.bot.as_mut().unwrap().throw(&physics, standing))
+                .collect()
+        };
+        let mut tournaments: Vec<_> = (0..2)
+            .map(|_| {
+                let mut tournament = Tournament::new(bots(), Format::parse("2:ladder").unwrap()).unwrap();
+                tournament.seed_bots(7);
+                tournament
+            })
+            .collect();
+        let first_game = first_throws(&tournaments[0]);
+        assert_eq!(first_game, first_throws(&tournaments[1]));
+        for tournament in &mut tournaments {
+            bowl(tournament, |_| 60);
+        }
+        let second_game = first_throws(&tournaments[0]);
+        assert_eq!(second_game, first_throws(&tournaments[1]));
+        // a game further on the bots don't miss the same way again
+        assert_ne!(first_game, second_game);
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+
+use super::phys::PinSet;
+use super::scoring::Rules;
+
+const INCH: f32 = 0.0254;
+const LB_TO_KG: f32 = 0.4536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantKind {
+    TenPin,
+    NinePin,
+    Candlepin,
+    Duckpin,
+    FivePin,
+}
+
+impl VariantKind {
+    pub const ALL: [VariantKind; 5] =
+        [VariantKind::TenPin, VariantKind::NinePin, VariantKind::Candlepin, VariantKind::Duckpin, VariantKind::FivePin];
+
+    // name used on the command line
+    pub fn id(self) -> &'static str {
+        match self {
+            VariantKind::TenPin => "ten",
+            VariantKind::NinePin => "nine",
+            VariantKind::Candlepin => "candle",
+            VariantKind::Duckpin => "duck",
+            VariantKind::FivePin => "five",
+        }
+    }
+
+    pub fn parse(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.id() == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PinSpec {
+    pub half_height: f32,
+    // at the belly
+    pub radius: f32,
+    pub mass: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BallSpec {
+    pub radius: f32,
+    // None when the bowler picks the weight
+    pub weight_lb: Option<f32>,
+}
+
+// One pin of the rack. x is towards the bowler's left, depth is measured back from the front pin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RackSpot {
+    pub number: u8,
+    pub x: f32,
+    pub depth: f32,
+    // what knocking the pin down is worth
+    pub value: u8,
+}
+
+// Everything that differs between the games played on a lane
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub kind: VariantKind,
+    pub name: &'static str,
+    pub pin: PinSpec,
+    pub ball: BallSpec,
+    pub rack: Vec<RackSpot>,
+    pub rules: Rules,
+    // fallen pins are left on the deck between the balls of a frame
+    pub deadwood_stays: bool,
+}
+
+//(pin number, row, column) of the ten-pin triangle: row 0 holds the head pin,
+//pins are numbered left to right from the bowler's view
+pub fn ten_pin_layout() -> impl Iterator<Item = (u8, usize, usize)> {
+    (0..4).flat_map(|row| (0..=row).map(move |col| ((row * (row + 1) / 2 + col + 1) as u8, row, col)))
+}
+
+// the ten-pin triangle with the given distance between neighbouring pins
+fn triangle(spacing: f32) -> Vec<RackSpot> {
+    let row_depth = spacing * 3f32.sqrt() / 2.0;
+    ten_pin_layout()
+        .map(|(number, row, col)| RackSpot {
+            number,
+            x: (row as f32 / 2.0 - col as f32) * spacing,
+            depth: row as f32 * row_depth,
+            value: 1,
+        })
+        .collect()
+}
+
+// nine pins on a square grid turned on its corner, rows of 1-2-3-2-1
+fn diamond(spacing: f32) -> Vec<RackSpot> {
+    let step = spacing / 2f32.sqrt();
+    let mut rack = Vec::new();
+    for row in 0..5 {
+        let pins = 3 - (row - 2i32).abs();
+        for col in 0..pins {
+            rack.push(RackSpot {
+                number: rack.len() as u8 + 1,
+                x: ((pins - 1) as f32 - 2.0 * col as f32) * step,
+                depth: row as f32 * step,
+                value: 1,
+            });
+        }
+    }
+    rack
+}
+
+// Canadian five-pin: a V of 2-3-5-3-2 points on the ten-pin spots 4, 2, 1, 3 and 6
+fn five_pin_v(spacing: f32) -> Vec<RackSpot> {
+    let triangle = triangle(spacing);
+    [(4, 2), (2, 3), (1, 5), (3, 3), (6, 2)]
+        .into_iter()
+        .enumerate()
+        .map(|(index, (spot, value))| RackSpot { number: index as u8 + 1, value, ..triangle[spot - 1] })
+        .collect()
+}
+
+impl Variant {
+    pub fn new(kind: VariantKind) -> Self {
+        let ten_pin = PinSpec { half_height: 7.5 * INCH, radius: 2.383 * INCH, mass: 1.53 };
+        let ten_pin_ball = BallSpec { radius: 4.25 * INCH, weight_lb: None };
+        match kind {
+            VariantKind::TenPin => Self {
+                kind,
+                name: "Ten-pin",
+                pin: ten_pin,
+                ball: ten_pin_ball,
+                rack: triangle(12.0 * INCH),
+                rules: Rules::TEN_PIN,
+                deadwood_stays: false,
+            },
+            VariantKind::NinePin => Self {
+                kind,
+                name: "Nine-pin",
+                pin: ten_pin,
+                ball: ten_pin_ball,
+                rack: diamond(12.0 * INCH),
+                rules: Rules { rack_value: 9, balls_per_frame: 2 },
+                deadwood_stays: false,
+            },
+            VariantKind::Candlepin => Self {
+                kind,
+                name: "Candlepin",
+                pin: PinSpec { half_height: 7.875 * INCH, radius: 1.47 * INCH, mass: 2.5 * LB_TO_KG },
+                ball: BallSpec { radius: 2.25 * INCH, weight_lb: Some(2.4) },
+                rack: triangle(12.0 * INCH),
+                rules: Rules { rack_value: 10, balls_per_frame: 3 },
+                deadwood_stays: true,
+            },
+            VariantKind::Duckpin => Self {
+                kind,
+                name: "Duckpin",
+                pin: PinSpec { half_height: 4.7 * INCH, radius: 2.06 * INCH, mass: 1.5 * LB_TO_KG },
+                ball: BallSpec { radius: 2.5 * INCH, weight_lb: Some(3.75) },
+                rack: triangle(12.0 * INCH),
+                rules: Rules { rack_value: 10, balls_per_frame: 3 },
+                deadwood_stays: false,
+            },
+            VariantKind::FivePin => Self {
+                kind,
+                name: "Five-pin",
+                pin: PinSpec { half_height: 6.25 * INCH, radius: 2.375 * INCH, mass: 2.25 * LB_TO_KG },
+                ball: BallSpec { radius: 2.5 * INCH, weight_lb: Some(3.5) },
+                rack: five_pin_v(12.0 * INCH),
+                rules: Rules { rack_value: 15, balls_per_frame: 3 },
+                deadwood_stays: false,
+            },
+        }
+    }
+
+    // Points the given pins are worth
+    pub fn value_of(&self, pins: PinSet) -> u8 {
+        self.rack.iter().filter(|spot| pins.contains(spot.number)).map(|spot| spot.value).sum()
+    }
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Self::new(VariantKind::TenPin)
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} pins, {} balls a frame", self.name, self.rack.len(), self.rules.balls_per_frame)?;
+        if self.deadwood_stays {
+            write!(f, ", deadwood stays")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lane::LaneSpec;
+    use super::super::phys::Physics;
+    use super::*;
+
+    fn numbers(variant: &Variant) -> Vec<u8> {
+        variant.rack.iter().map(|spot| spot.number).collect()
+    }
+
+    #[test]
+    fn racks_hold_the_pins_of_their_game() {
+        let ten = Variant::new(VariantKind::TenPin);
+        assert_eq!(numbers(&ten), (1..=10).collect::<Vec<_>>());
+        // the 7 at the back on the left, the 10 on the right
+        assert_eq!((ten.rack[6].x > 0.0, ten.rack[9].x < 0.0), (true, true));
+        assert_eq!(ten.rack[6].depth, ten.rack[9].depth);
+        let nine = Variant::new(VariantKind::NinePin);
+        assert_eq!(numbers(&nine), (1..=9).collect::<Vec<_>>());
+        // the diamond: 1-2-3-2-1 with the 5 in the middle
+        let rows: Vec<usize> = nine.rack.chunk_by(|a, b| a.depth == b.depth).map(<[RackSpot]>::len).collect();
+        assert_eq!(rows, [1, 2, 3, 2, 1]);
+        assert_eq!(nine.rack[4].x, 0.0);
+        for kind in [VariantKind::Candlepin, VariantKind::Duckpin] {
+            assert_eq!(numbers(&Variant::new(kind)), numbers(&ten), "{kind:?}");
+        }
+        // five-pin stands on the ten-pin spots 4, 2, 1, 3 and 6 and counts 2-3-5-3-2
+        let five = Variant::new(VariantKind::FivePin);
+        let spots: Vec<(f32, f32)> = five.rack.iter().map(|spot| (spot.x, spot.depth)).collect();
+        assert_eq!(spots, [4, 2, 1, 3, 6].map(|pin: usize| (ten.rack[pin - 1].x, ten.rack[pin - 1].depth)));
+        assert_eq!(five.rack.iter().map(|spot| spot.value).collect::<Vec<_>>(), [2, 3, 5, 3, 2]);
+    }
+
+    #[test]
+    fn a_full_rack_is_worth_the_rack_value() {
+        for kind in VariantKind::ALL {
+            let variant = Variant::new(kind);
+            let mut all = PinSet::default();
+            for spot in &variant.rack {
+                all.insert(spot.number);
+            }
+            assert_eq!(variant.value_of(all), variant.rules.rack_value, "{kind:?}");
+            assert_eq!(VariantKind::parse(kind.id()), Some(kind));
+        }
+        let mut head_pin = PinSet::default();
+        head_pin.insert(3);
+        assert_eq!(Variant::new(VariantKind::FivePin).value_of(head_pin), 5);
+    }
+
+    #[test]
+    fn the_lane_sets_up_the_rack_of_the_variant() {
+        for kind in VariantKind::ALL {
+            let variant = Variant::new(kind);
+            let mut physics = Physics::new(LaneSpec::REGULATION);
+            physics.set_variant(variant.clone());
+            physics.build_colliders();
+            assert_eq!(physics.all_pins().iter().collect::<Vec<_>>(), numbers(&variant), "{kind:?}");
+            assert_eq!(physics.standing_pins(), physics.all_pins(), "{kind:?}");
+            // the front pin stands on the head pin spot of every game
+            let front = variant.rack.iter().find(|spot| spot.depth == 0.0).unwrap();
+            let head_pin = physics.pin_spot(front.number).unwrap();
+            assert!((head_pin.z - LaneSpec::REGULATION.foul_line_to_head_pin).abs() < 1e-4, "{kind:?} {head_pin:?}");
+        }
+    }
+}